@@ -0,0 +1,199 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use safe_txe_circuit::{
+    Input, PrivateInput, PrivateRecipient, PublicInput, PublicRecipient,
+    bench::ecdh,
+    circuit, encrypt, hex,
+    safe::{Operation, SafeTransaction},
+};
+use std::{borrow::Cow, hint::black_box};
+
+const PUBLIC: &str = "0xf90145a0f25354b37bde8dfdfbeb638a3e010cdd09ff6a319dbfb0ab12589de2\
+                      5d3352be820539b84bbf39261d44916617d853e3538b2a096ffd7ce3236210e6\
+                      13ed4decca6e32e4696c4f8c24734cce38a1ce3a1500f74f58b575188b33d4e8\
+                      ed8961aa9f0f6407db788e7f1fd5af28db6001fb8cb05c984165f2d23a28000d\
+                      4b9008e67b91dcd38c7a1f48b93b59ffe1b8f8b4f83a98590a3a98e58dadf522\
+                      baa91357ec1d0f4f5305c6dd885745a0fb74a081098bcfe6e6c1840bea1194b9\
+                      2c7e41912fc2347cbe0cbc7fa4a4857af83a986de31be4920402f1348ebd4431\
+                      6a35ca7a0af9657d863b03a01083b3b5529465bb436d52ccf5c887da31a687ad\
+                      778ffe0c0bc58b0d81811333f83a983f04b1dd42337e71b0421be845c9bc1e2a\
+                      7fcf9c45c62681a072cda02de475ad6f654f66796160377c65a26684a4f1d4b2\
+                      9dcb225ca180bd29";
+
+const PRIVATE: &str = "0xf9012cb84bf84994a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a102840304\
+                       05060107080994a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a294a3a3a3a3\
+                       a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a390c3ba3d49dd84aaf39f49478324bc31\
+                       69f8ccf842a032487b2e70917797e376aed50c85902eea2c42ba4fad257a6c6b\
+                       b93e47e80b2fa068dd94fb8d7ca504c59fdcfd1413d7202eecbbb252ab3bbcdb\
+                       6e4697b4d3e463f842a0029bfe0f900e8ac0e6a98aa3ffde0ad93b46f52a5a37\
+                       43b9ce88296ca2385168a02065df9b0385a913255081ca19e9153391e41e3ff8\
+                       f3c2426c2878114cd2be66f842a0201ef1b77e2b56130b358749711812f6fcc6\
+                       d1543c425c32f5f5c0408731f20aa0b01923b73b27127f61932b21501a516475\
+                       922f0aa50f5b56cff2eeafa0521c4b";
+
+const RECIPIENT_COUNTS: [usize; 4] = [1, 4, 16, 64];
+const DATA_SIZES: [usize; 3] = [0, 1024, 64 * 1024];
+
+const CONTENT_ENCRYPTION_KEY: [u8; 16] = [0x42; 16];
+const IV: [u8; 12] = [0x24; 12];
+const NONCE: [u8; 32] = [0x01; 32];
+
+fn transaction(data_size: usize) -> SafeTransaction<'static> {
+    SafeTransaction {
+        to: [0xa1; 20],
+        value: [0; 32],
+        data: Cow::Owned(vec![0xda; data_size]),
+        operation: Operation::Call,
+        safe_tx_gas: [0; 32],
+        gas_gas: [0; 32],
+        gas_price: [0; 32],
+        gas_token: [0; 20],
+        refund_reciver: [0; 20],
+    }
+}
+
+/// Builds a valid circuit input for a transaction with `data_size` bytes of
+/// calldata, encrypted for `recipient_count` recipients.
+fn input(recipient_count: usize, data_size: usize) -> Input<'static> {
+    let transaction = transaction(data_size);
+    let encoded = transaction.encode();
+    let Ok((ciphertext, tag)) = encrypt::content(&encoded, CONTENT_ENCRYPTION_KEY, IV, None) else {
+        panic!("content encryption failed");
+    };
+
+    let (public, private) = (1..=recipient_count as u32)
+        .map(|i| {
+            let mut ephemeral_private_key = [0; 32];
+            ephemeral_private_key[..4].copy_from_slice(&i.to_be_bytes());
+            let mut recipient_private_key = [0xff; 32];
+            recipient_private_key[..4].copy_from_slice(&i.to_be_bytes());
+            let public_key = ecdh::public_key(recipient_private_key);
+            let shared_secret = ecdh::shared_secret(ephemeral_private_key, public_key);
+            let Ok(encrypted_key) = encrypt::key(CONTENT_ENCRYPTION_KEY, shared_secret) else {
+                panic!("key encryption failed");
+            };
+            (
+                PublicRecipient {
+                    encrypted_key,
+                    ephemeral_public_key: ecdh::public_key(ephemeral_private_key),
+                },
+                PrivateRecipient {
+                    public_key,
+                    ephemeral_private_key,
+                },
+            )
+        })
+        .unzip::<_, _, Vec<_>, Vec<_>>();
+
+    Input {
+        public: PublicInput {
+            struct_hash: transaction.struct_hash(NONCE),
+            nonce: NONCE,
+            ciphertext: ciphertext.into(),
+            iv: IV,
+            tag,
+            recipients: public.into(),
+        },
+        private: PrivateInput {
+            transaction: encoded.into(),
+            content_encryption_key: CONTENT_ENCRYPTION_KEY,
+            recipients: private.into(),
+        },
+    }
+}
+
+fn sampled(c: &mut Criterion) {
+    let (Ok(public), Ok(private)) = (hex::decode(PUBLIC), hex::decode(PRIVATE)) else {
+        panic!("invalid sampled input hex");
+    };
+    let Ok(input) = Input::decode_borrowed(&public, &private) else {
+        panic!("invalid sampled input encoding");
+    };
+    c.bench_function("circuit sampled vector", |b| {
+        b.iter(|| circuit(black_box(&input)).is_ok())
+    });
+}
+
+fn circuit_recipients(c: &mut Criterion) {
+    let mut group = c.benchmark_group("circuit by recipient count");
+    for count in RECIPIENT_COUNTS {
+        let input = input(count, 0);
+        assert!(circuit(&input).is_ok());
+        group.throughput(Throughput::Elements(count as _));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &input, |b, input| {
+            b.iter(|| circuit(black_box(input)).is_ok())
+        });
+    }
+    group.finish();
+}
+
+fn circuit_data(c: &mut Criterion) {
+    let mut group = c.benchmark_group("circuit by data size");
+    for size in DATA_SIZES {
+        let input = input(1, size);
+        assert!(circuit(&input).is_ok());
+        group.throughput(Throughput::Bytes(size as _));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter(|| circuit(black_box(input)).is_ok())
+        });
+    }
+    group.finish();
+}
+
+fn content(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encrypt::content");
+    for size in DATA_SIZES {
+        let plaintext = transaction(size).encode();
+        // Report throughput in AES and GHASH block operations, as these
+        // dominate the cost of content encryption in a prover.
+        let blocks = encrypt::gcm_block_count(plaintext.len(), encrypt::AAD.len());
+        group.throughput(Throughput::Elements(blocks as _));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &plaintext,
+            |b, plaintext| {
+                b.iter(|| encrypt::content(black_box(plaintext), CONTENT_ENCRYPTION_KEY, IV, None))
+            },
+        );
+    }
+    group.finish();
+}
+
+fn struct_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("struct_hash");
+    for size in DATA_SIZES {
+        let transaction = transaction(size);
+        group.throughput(Throughput::Bytes(size as _));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &transaction,
+            |b, transaction| b.iter(|| black_box(transaction).struct_hash(NONCE)),
+        );
+    }
+    group.finish();
+}
+
+fn primitives(c: &mut Criterion) {
+    let shared_secret = ecdh::shared_secret([0x11; 32], ecdh::public_key([0x22; 32]));
+    c.bench_function("encrypt::key", |b| {
+        b.iter(|| encrypt::key(CONTENT_ENCRYPTION_KEY, black_box(shared_secret)).is_ok())
+    });
+    c.bench_function("encrypt::kdf", |b| {
+        b.iter(|| encrypt::kdf(black_box(shared_secret)))
+    });
+
+    let public_key = ecdh::public_key([0x22; 32]);
+    c.bench_function("ecdh::shared_secret", |b| {
+        b.iter(|| ecdh::shared_secret(black_box([0x11; 32]), black_box(public_key)))
+    });
+}
+
+criterion_group!(
+    benches,
+    sampled,
+    circuit_recipients,
+    circuit_data,
+    content,
+    struct_hash,
+    primitives,
+);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use safe_txe_circuit::{PublicInput, hex};
+use std::hint::black_box;
+
+const PUBLIC: &str = "0xf90145a0f25354b37bde8dfdfbeb638a3e010cdd09ff6a319dbfb0ab12589de2\
+                      5d3352be820539b84bbf39261d44916617d853e3538b2a096ffd7ce3236210e6\
+                      13ed4decca6e32e4696c4f8c24734cce38a1ce3a1500f74f58b575188b33d4e8\
+                      ed8961aa9f0f6407db788e7f1fd5af28db6001fb8cb05c984165f2d23a28000d\
+                      4b9008e67b91dcd38c7a1f48b93b59ffe1b8f8b4f83a98590a3a98e58dadf522\
+                      baa91357ec1d0f4f5305c6dd885745a0fb74a081098bcfe6e6c1840bea1194b9\
+                      2c7e41912fc2347cbe0cbc7fa4a4857af83a986de31be4920402f1348ebd4431\
+                      6a35ca7a0af9657d863b03a01083b3b5529465bb436d52ccf5c887da31a687ad\
+                      778ffe0c0bc58b0d81811333f83a983f04b1dd42337e71b0421be845c9bc1e2a\
+                      7fcf9c45c62681a072cda02de475ad6f654f66796160377c65a26684a4f1d4b2\
+                      9dcb225ca180bd29";
+
+fn public_input(c: &mut Criterion) {
+    let Ok(encoded) = hex::decode(PUBLIC) else {
+        panic!("invalid public input hex");
+    };
+    c.bench_function("decode public input", |b| {
+        b.iter(|| PublicInput::decode(black_box(&encoded)).is_ok())
+    });
+}
+
+criterion_group!(benches, public_input);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use safe_txe_circuit::safe::{Operation, SafeTransaction, StructHasher};
+use std::{borrow::Cow, hint::black_box};
+
+fn batch() -> Vec<(SafeTransaction<'static>, [u8; 32])> {
+    (0..100_u8)
+        .map(|i| {
+            let transaction = SafeTransaction {
+                to: [i; 20],
+                value: [i; 32],
+                data: Cow::Owned(vec![i; 4 + i as usize]),
+                operation: Operation::Call,
+                safe_tx_gas: [0; 32],
+                gas_gas: [0; 32],
+                gas_price: [0; 32],
+                gas_token: [0; 20],
+                refund_reciver: [0; 20],
+            };
+            let mut nonce = [0; 32];
+            nonce[31] = i;
+            (transaction, nonce)
+        })
+        .collect()
+}
+
+fn struct_hash_batch(c: &mut Criterion) {
+    let batch = batch();
+    let mut group = c.benchmark_group("struct hash batch of 100");
+    group.bench_function("fresh hasher", |b| {
+        b.iter(|| {
+            for (transaction, nonce) in &batch {
+                black_box(transaction.struct_hash(*nonce));
+            }
+        })
+    });
+    group.bench_function("reused hasher", |b| {
+        b.iter(|| {
+            let hasher = StructHasher::new();
+            for (transaction, nonce) in &batch {
+                black_box(hasher.struct_hash(transaction, *nonce));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn struct_hash_encoded(c: &mut Criterion) {
+    let batch = batch()
+        .into_iter()
+        .map(|(transaction, nonce)| (transaction.encode(), nonce))
+        .collect::<Vec<_>>();
+    let mut group = c.benchmark_group("struct hash of encoded batch of 100");
+    group.bench_function("decode then hash", |b| {
+        b.iter(|| {
+            let hasher = StructHasher::new();
+            for (encoded, nonce) in &batch {
+                let Ok(transaction) = SafeTransaction::decode(encoded) else {
+                    panic!("invalid transaction encoding");
+                };
+                black_box(hasher.struct_hash(&transaction, *nonce));
+            }
+        })
+    });
+    group.bench_function("hash encoded", |b| {
+        b.iter(|| {
+            let hasher = StructHasher::new();
+            for (encoded, nonce) in &batch {
+                black_box(hasher.struct_hash_encoded(encoded, *nonce).is_ok());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, struct_hash_batch, struct_hash_encoded);
+criterion_main!(benches);
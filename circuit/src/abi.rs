@@ -0,0 +1,132 @@
+//! Solidity ABI encoding of the public input for on-chain verifiers.
+
+use crate::{PublicInput, u256};
+
+impl PublicInput<'_> {
+    /// ABI-encodes the public input as the Solidity parameters
+    /// `(bytes32 structHash, uint256 nonce, bytes ciphertext, bytes12 iv,
+    /// bytes16 tag, (bytes24,bytes32)[] recipients)`, as expected by the
+    /// on-chain verifier.
+    pub fn to_abi(&self) -> Vec<u8> {
+        const HEAD_LEN: usize = 6 * 32;
+        let ciphertext_len = 32 + self.ciphertext.len().next_multiple_of(32);
+        let recipients_len = 32 + self.recipients.len() * 64;
+
+        let mut encoded = Vec::with_capacity(HEAD_LEN + ciphertext_len + recipients_len);
+        encoded.extend_from_slice(&self.struct_hash);
+        encoded.extend_from_slice(&self.nonce);
+        encoded.extend_from_slice(&uint(HEAD_LEN));
+        encoded.extend_from_slice(&fixed_bytes(&self.iv));
+        encoded.extend_from_slice(&fixed_bytes(&self.tag));
+        encoded.extend_from_slice(&uint(HEAD_LEN + ciphertext_len));
+
+        encoded.extend_from_slice(&uint(self.ciphertext.len()));
+        encoded.extend_from_slice(&self.ciphertext);
+        encoded.resize(HEAD_LEN + ciphertext_len, 0);
+
+        encoded.extend_from_slice(&uint(self.recipients.len()));
+        for recipient in &*self.recipients {
+            encoded.extend_from_slice(&fixed_bytes(&recipient.encrypted_key));
+            encoded.extend_from_slice(&recipient.ephemeral_public_key);
+        }
+        encoded
+    }
+}
+
+/// Encodes a `uint256` word.
+fn uint(value: usize) -> [u8; 32] {
+    u256::from_u64(value as u64)
+}
+
+/// Encodes a left-aligned `bytesN` word.
+fn fixed_bytes<const N: usize>(value: &[u8; N]) -> [u8; 32] {
+    let mut word = [0; 32];
+    word[..N].copy_from_slice(value);
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PublicRecipient, hex, tests::input};
+    use alloy_primitives::{Bytes, FixedBytes, U256};
+    use alloy_sol_types::SolValue as _;
+    use std::borrow::Cow;
+
+    /// The Solidity parameters of the on-chain verifier.
+    type Params = (
+        FixedBytes<32>,
+        U256,
+        Bytes,
+        FixedBytes<12>,
+        FixedBytes<16>,
+        Vec<(FixedBytes<24>, FixedBytes<32>)>,
+    );
+
+    fn params(public: &PublicInput) -> Params {
+        (
+            public.struct_hash.into(),
+            U256::from_be_bytes(public.nonce),
+            Bytes::copy_from_slice(&public.ciphertext),
+            public.iv.into(),
+            public.tag.into(),
+            public
+                .recipients
+                .iter()
+                .map(|recipient| {
+                    (
+                        recipient.encrypted_key.into(),
+                        recipient.ephemeral_public_key.into(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    /// Checks the encoding against the `alloy-sol-types` ABI encoder and
+    /// decoder.
+    fn check(public: &PublicInput) {
+        let encoded = public.to_abi();
+        assert_eq!(encoded, params(public).abi_encode_params());
+        assert_eq!(Params::abi_decode_params(&encoded).unwrap(), params(public));
+    }
+
+    #[test]
+    fn test_to_abi() {
+        let public = PublicInput {
+            struct_hash: [0x11; 32],
+            nonce: u256::from_u64(5),
+            ciphertext: Cow::Borrowed(b"hello"),
+            iv: [0x22; 12],
+            tag: [0x33; 16],
+            recipients: Cow::Owned(vec![PublicRecipient {
+                encrypted_key: [0x44; 24],
+                ephemeral_public_key: [0x55; 32],
+            }]),
+        };
+        let expected = hex::decode(concat!(
+            "0x",
+            "1111111111111111111111111111111111111111111111111111111111111111",
+            "0000000000000000000000000000000000000000000000000000000000000005",
+            "00000000000000000000000000000000000000000000000000000000000000c0",
+            "2222222222222222222222220000000000000000000000000000000000000000",
+            "3333333333333333333333333333333300000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000100",
+            "0000000000000000000000000000000000000000000000000000000000000005",
+            "68656c6c6f000000000000000000000000000000000000000000000000000000",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "4444444444444444444444444444444444444444444444440000000000000000",
+            "5555555555555555555555555555555555555555555555555555555555555555",
+        ))
+        .unwrap();
+        assert_eq!(public.to_abi(), expected);
+        check(&public);
+
+        check(&input().public);
+        check(&PublicInput {
+            ciphertext: Cow::Owned(vec![0x66; 64]),
+            recipients: Cow::Owned(vec![]),
+            ..public
+        });
+    }
+}
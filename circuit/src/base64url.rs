@@ -0,0 +1,126 @@
+//! Unpadded base64url string encoding and decoding.
+
+use std::{error, fmt, iter};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes bytes into an unpadded base64url string.
+pub fn encode(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let mut word = [0; 4];
+        word[1..][..chunk.len()].copy_from_slice(chunk);
+        let word = u32::from_be_bytes(word);
+        for i in 0..=chunk.len() {
+            let sextet = (word >> (18 - 6 * i)) & 0x3f;
+            s.push(ALPHABET[sextet as usize] as char);
+        }
+    }
+    s
+}
+
+/// Decodes an unpadded base64url string into bytes.
+pub fn decode(s: &str) -> Result<Vec<u8>, Error> {
+    let mut bytes = vec![0; decoded_len(s.len()).ok_or(Error)?];
+    decode_into(s, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decodes an unpadded base64url string into a caller provided buffer,
+/// returning the number of bytes written.
+///
+/// This avoids an intermediate allocation when decoding large values. The
+/// buffer may be larger than the decoded bytes, but not smaller.
+pub fn decode_into(s: &str, out: &mut [u8]) -> Result<usize, Error> {
+    let len = decoded_len(s.len()).ok_or(Error)?;
+    let (out_chunks, out_rest) = out.get_mut(..len).ok_or(Error)?.as_chunks_mut::<3>();
+    let (chunks, rest) = s.as_bytes().as_chunks::<4>();
+    for (&chunk, out) in iter::zip(chunks, out_chunks) {
+        let word = chunk
+            .iter()
+            .try_fold(0_u32, |word, &c| Ok((word << 6) | sextet(c)?))?;
+        out.copy_from_slice(&word.to_be_bytes()[1..]);
+    }
+    if rest.is_empty() {
+        return Ok(len);
+    }
+    let word = rest
+        .iter()
+        .try_fold(0_u32, |word, &c| Ok((word << 6) | sextet(c)?))?;
+    let word = word << (6 * (4 - rest.len()));
+    let [_, tail @ ..] = word.to_be_bytes();
+    // Reject non-canonical encodings with trailing bits set.
+    if tail[out_rest.len()..].iter().any(|&b| b != 0) {
+        return Err(Error);
+    }
+    out_rest.copy_from_slice(&tail[..out_rest.len()]);
+    Ok(len)
+}
+
+/// Returns the number of bytes an unpadded base64url string of length `len`
+/// decodes to, or `None` if no string has that length.
+pub fn decoded_len(len: usize) -> Option<usize> {
+    match len % 4 {
+        1 => None,
+        rest => Some(len / 4 * 3 + rest.saturating_sub(1)),
+    }
+}
+
+fn sextet(c: u8) -> Result<u32, Error> {
+    match c {
+        b'A'..=b'Z' => Ok((c - b'A') as u32),
+        b'a'..=b'z' => Ok((c - b'a' + 26) as u32),
+        b'0'..=b'9' => Ok((c - b'0' + 52) as u32),
+        b'-' => Ok(62),
+        b'_' => Ok(63),
+        _ => Err(Error),
+    }
+}
+
+/// A base64url decoding error.
+#[derive(Debug)]
+pub struct Error;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid unpadded base64url string")
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error() {
+        let err: Box<dyn error::Error> = decode("A").unwrap_err().into();
+        assert_eq!(err.to_string(), "invalid unpadded base64url string");
+    }
+
+    #[test]
+    fn test_decode_into() {
+        let mut out = [0; 5];
+        assert!(matches!(decode_into("AQIDBAU", &mut out), Ok(5)));
+        assert_eq!(out, [1, 2, 3, 4, 5]);
+
+        let mut out = [0xff; 8];
+        assert!(matches!(decode_into("AQIDBAU", &mut out), Ok(5)));
+        assert_eq!(out, [1, 2, 3, 4, 5, 0xff, 0xff, 0xff]);
+
+        let mut out = [0; 4];
+        assert!(decode_into("AQIDBAU", &mut out).is_err());
+        assert!(decode_into("AQID", &mut out[..2]).is_err());
+    }
+
+    #[test]
+    fn test_decode_into_invalid() {
+        let mut out = [0; 8];
+        assert!(decode_into("AQ+DBA", &mut out).is_err());
+        assert!(decode_into("AQID/A", &mut out).is_err());
+        assert!(decode_into("AQIDB", &mut out).is_err());
+        assert!(decode_into("AQ==", &mut out).is_err());
+        assert!(decode_into("AR", &mut out).is_err());
+    }
+}
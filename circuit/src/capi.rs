@@ -1,7 +1,10 @@
 //! External C interface for the circuit.
 
-use crate::{Input, PrivateInput, PrivateRecipient, PublicInput, PublicRecipient, hex, rlp};
-use std::ffi::{CStr, c_char};
+use crate::{ContentAlg, Input, KeyAlg, hex};
+use std::{
+    ffi::{CStr, c_char},
+    fmt,
+};
 
 /// Circuit execution result.
 #[repr(C)]
@@ -12,6 +15,22 @@ pub enum CircuitResult {
     Failure = -1,
 }
 
+impl CircuitResult {
+    /// Returns a static description of the result.
+    pub fn as_c_str(&self) -> &'static CStr {
+        match self {
+            Self::Success => c"success",
+            Self::Failure => c"failure",
+        }
+    }
+}
+
+impl fmt::Display for CircuitResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_c_str().to_str().map_err(|_| fmt::Error)?)
+    }
+}
+
 /// Executes the Safe transaction circuit.
 ///
 /// # Safety
@@ -23,68 +42,87 @@ pub unsafe extern "C" fn txe_circuit(
     public: *const c_char,
     private: *const c_char,
 ) -> CircuitResult {
-    let Some(public) = arg(public, |decoder| {
-        Ok(PublicInput {
-            struct_hash: decoder.bytes_array()?,
-            nonce: decoder.uint()?,
-            ciphertext: decoder.bytes()?.to_vec().into(),
-            iv: decoder.bytes_array()?,
-            tag: decoder.bytes_array()?,
-            recipients: decoder
-                .vec(|item| {
-                    item.decode_struct(|decoder| {
-                        Ok(PublicRecipient {
-                            encrypted_key: decoder.bytes_array()?,
-                            ephemeral_public_key: decoder.bytes_array()?,
-                        })
-                    })
-                })?
-                .into(),
-        })
-    }) else {
+    unsafe { args_circuit(public, private, KeyAlg::default()) }
+}
+
+unsafe fn args_circuit(
+    public: *const c_char,
+    private: *const c_char,
+    key_alg: KeyAlg,
+) -> CircuitResult {
+    let Some(public) = arg(public) else {
         return CircuitResult::Failure;
     };
-
-    let Some(private) = arg(private, |decoder| {
-        Ok(PrivateInput {
-            transaction: decoder.bytes()?.to_vec().into(),
-            content_encryption_key: decoder.bytes_array()?,
-            recipients: decoder
-                .vec(|item| {
-                    item.decode_struct(|decoder| {
-                        Ok(PrivateRecipient {
-                            public_key: decoder.bytes_array()?,
-                            ephemeral_private_key: decoder.bytes_array()?,
-                        })
-                    })
-                })?
-                .into(),
-        })
-    }) else {
+    let Some(private) = arg(private) else {
+        return CircuitResult::Failure;
+    };
+    let Ok(input) = Input::decode_borrowed(&public, &private) else {
         return CircuitResult::Failure;
     };
+    input_circuit(&input, key_alg)
+}
 
-    let input = Input { public, private };
-    match crate::circuit(&input) {
+fn input_circuit(input: &Input, key_alg: KeyAlg) -> CircuitResult {
+    match crate::circuit_key_alg(input, key_alg) {
         Ok(()) => CircuitResult::Success,
         Err(_) => CircuitResult::Failure,
     }
 }
 
-fn arg<T, F>(s: *const c_char, f: F) -> Option<T>
-where
-    F: FnOnce(&mut rlp::Decoder) -> Result<T, rlp::Error>,
-{
+/// Returns a static, null-terminated description of a circuit result for
+/// logging. The returned string must not be freed.
+#[cfg_attr(not(target_arch = "wasm32"), unsafe(no_mangle))]
+pub extern "C" fn txe_result_str(result: CircuitResult) -> *const c_char {
+    result.as_c_str().as_ptr()
+}
+
+/// The algorithms the circuit is configured to expect.
+#[derive(Default)]
+pub struct Algorithms {
+    /// The content encryption algorithm.
+    pub content: ContentAlg,
+    /// The key management algorithm.
+    pub key: KeyAlg,
+}
+
+impl Algorithms {
+    /// Selects the algorithms from `NAME=value` environment entries. The
+    /// `TXE_ENC` variable selects the content encryption algorithm and
+    /// `TXE_ALG` the key management algorithm, each falling back to its
+    /// default when unset. Returns `None` for unsupported algorithms, while
+    /// other variables are ignored even if they are not valid UTF-8.
+    pub fn from_environ<'a, I>(environ: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut algorithms = Self::default();
+        for var in environ {
+            let Some(eq) = var.iter().position(|&b| b == b'=') else {
+                continue;
+            };
+            let (name, value) = (&var[..eq], &var[eq + 1..]);
+            let value = || str::from_utf8(value).ok();
+            match name {
+                b"TXE_ENC" => algorithms.content = ContentAlg::from_name(value()?)?,
+                b"TXE_ALG" => algorithms.key = KeyAlg::from_name(value()?)?,
+                _ => {}
+            }
+        }
+        Some(algorithms)
+    }
+}
+
+fn arg(s: *const c_char) -> Option<Vec<u8>> {
     let s = unsafe { CStr::from_ptr(s) }.to_str().ok()?;
-    let hex = hex::decode(s).ok()?;
-    let mut decoder = rlp::Decoder::new(&hex);
-    decoder.decode_struct(f).ok()
+    hex::decode(s).ok()
 }
 
 #[cfg(target_arch = "wasm32")]
 mod wasm {
+    use super::{Algorithms, CircuitResult};
+    use crate::{Input, KeyAlg};
     use std::{
-        ffi::c_char,
+        ffi::{CStr, c_char},
         mem::MaybeUninit,
         panic::{self, PanicHookInfo},
     };
@@ -93,9 +131,18 @@ mod wasm {
     unsafe extern "C" {
         fn args_get(argv: *mut *mut c_char, argb: *mut c_char) -> i32;
         fn args_sizes_get(argc: *mut usize, argb_size: *mut usize) -> i32;
+        fn environ_get(environ: *mut *mut c_char, environ_buf: *mut c_char) -> i32;
+        fn environ_sizes_get(environc: *mut usize, environ_buf_size: *mut usize) -> i32;
+        fn fd_read(fd: i32, iovs: *const Iovec, iovs_len: usize, nread: *mut usize) -> i32;
         fn proc_exit(code: i32) -> !;
     }
 
+    #[repr(C)]
+    struct Iovec {
+        buf: *mut u8,
+        buf_len: usize,
+    }
+
     #[cfg(debug_assertions)]
     #[link(wasm_import_module = "env")]
     unsafe extern "C" {
@@ -106,6 +153,18 @@ mod wasm {
     pub unsafe extern "C" fn start() -> ! {
         panic::set_hook(Box::new(panic_hook));
 
+        #[cfg(feature = "self-test")]
+        if crate::encrypt::self_test().is_err() {
+            exit(1);
+        }
+
+        // The circuit only implements a single content encryption algorithm,
+        // so its selection only serves to reject configurations it cannot
+        // verify.
+        let Some(algorithms) = (unsafe { algorithms() }) else {
+            exit(1);
+        };
+
         let (argc, argb_size) = unsafe {
             let mut argc = MaybeUninit::uninit();
             let mut argb_size = MaybeUninit::uninit();
@@ -115,26 +174,86 @@ mod wasm {
             };
             (argc.assume_init(), argb_size.assume_init())
         };
-        if argc != 3 {
-            exit(1);
-        }
+        let result = match argc {
+            // Without arguments, read the combined input from stdin, which
+            // avoids argument length limits for large inputs.
+            1 => stdin_circuit(algorithms.key),
+            3 => unsafe { args_circuit(argb_size, algorithms.key) },
+            _ => exit(1),
+        };
 
+        exit(result as _);
+    }
+
+    unsafe fn args_circuit(argb_size: usize, key_alg: KeyAlg) -> CircuitResult {
         let mut argb = Box::<[c_char]>::new_uninit_slice(argb_size);
-        let result = {
-            let argv = unsafe {
-                let mut argv = MaybeUninit::<[*mut c_char; 3]>::uninit();
-                let result = args_get(argv.as_mut_ptr().cast(), argb.as_mut_ptr().cast());
+        let argv = unsafe {
+            let mut argv = MaybeUninit::<[*mut c_char; 3]>::uninit();
+            let result = args_get(argv.as_mut_ptr().cast(), argb.as_mut_ptr().cast());
+            if result != 0 {
+                exit(1);
+            }
+            argv.assume_init()
+        };
+
+        let [_, public, private] = argv;
+        unsafe { super::args_circuit(public, private, key_alg) }
+    }
+
+    fn stdin_circuit(key_alg: KeyAlg) -> CircuitResult {
+        let Some(input) = read_stdin().and_then(|encoded| Input::decode(&encoded).ok()) else {
+            return CircuitResult::Failure;
+        };
+        super::input_circuit(&input, key_alg)
+    }
+
+    fn read_stdin() -> Option<Vec<u8>> {
+        let mut buf = Vec::new();
+        loop {
+            buf.reserve(4096);
+            let spare = buf.spare_capacity_mut();
+            let iov = Iovec {
+                buf: spare.as_mut_ptr().cast(),
+                buf_len: spare.len(),
+            };
+            let nread = unsafe {
+                let mut nread = MaybeUninit::uninit();
+                let result = fd_read(0, &iov, 1, nread.as_mut_ptr());
                 if result != 0 {
-                    exit(1);
+                    return None;
                 }
-                argv.assume_init()
+                nread.assume_init()
             };
+            if nread == 0 {
+                return Some(buf);
+            }
+            unsafe { buf.set_len(buf.len() + nread) };
+        }
+    }
 
-            let [_, public, private] = argv;
-            unsafe { super::txe_circuit(public, private) }
+    unsafe fn algorithms() -> Option<Algorithms> {
+        let (environc, environ_buf_size) = unsafe {
+            let mut environc = MaybeUninit::uninit();
+            let mut environ_buf_size = MaybeUninit::uninit();
+            let result = environ_sizes_get(environc.as_mut_ptr(), environ_buf_size.as_mut_ptr());
+            if result != 0 {
+                return None;
+            }
+            (environc.assume_init(), environ_buf_size.assume_init())
         };
 
-        exit(result as _);
+        let mut environ = vec![std::ptr::null_mut::<c_char>(); environc];
+        let mut environ_buf = Box::<[c_char]>::new_uninit_slice(environ_buf_size);
+        let result = unsafe { environ_get(environ.as_mut_ptr(), environ_buf.as_mut_ptr().cast()) };
+        if result != 0 {
+            return None;
+        }
+
+        Algorithms::from_environ(
+            environ
+                .iter()
+                .map(|&var| unsafe { CStr::from_ptr(var) }.to_bytes()),
+        )
     }
 
     fn exit(code: i32) -> ! {
@@ -185,4 +304,55 @@ mod tests {
 
         unsafe { txe_circuit(public.as_ptr(), private.as_ptr()) };
     }
+
+    #[test]
+    fn test_circuit_malformed() {
+        let private = c"0xc0";
+        for public in [
+            c"",
+            c"0x",
+            c"0xzz",
+            c"0xf901",
+            c"0xc0",
+            c"0xc3808080",
+            c"\xff\xfe",
+        ] {
+            let result = unsafe { txe_circuit(public.as_ptr(), private.as_ptr()) };
+            assert!(matches!(result, CircuitResult::Failure));
+        }
+    }
+
+    #[test]
+    fn test_result_str() {
+        let success = unsafe { CStr::from_ptr(txe_result_str(CircuitResult::Success)) };
+        assert_eq!(success, c"success");
+        let failure = unsafe { CStr::from_ptr(txe_result_str(CircuitResult::Failure)) };
+        assert_eq!(failure, c"failure");
+
+        assert_eq!(CircuitResult::Success.to_string(), "success");
+        assert_eq!(CircuitResult::Failure.to_string(), "failure");
+    }
+
+    #[test]
+    fn test_algorithms_from_environ() {
+        let algorithms = Algorithms::from_environ([
+            b"PATH=/bin".as_slice(),
+            b"LANG=\xff",
+            b"TXE_ENC=A128GCM",
+            b"TXE_ALG=ECDH-ES+A128KW",
+        ])
+        .unwrap();
+        assert!(algorithms.content == ContentAlg::A128Gcm);
+        assert!(algorithms.key == KeyAlg::EcdhEsA128Kw);
+
+        let algorithms = Algorithms::from_environ([]).unwrap();
+        assert!(algorithms.content == ContentAlg::default());
+        assert!(algorithms.key == KeyAlg::default());
+
+        assert!(Algorithms::from_environ([b"TXE_ENC=A256GCM".as_slice()]).is_none());
+        assert!(Algorithms::from_environ([b"TXE_ENC=\xff".as_slice()]).is_none());
+        let algorithms = Algorithms::from_environ([b"TXE_ALG=dir".as_slice()]).unwrap();
+        assert!(algorithms.key == KeyAlg::Direct);
+        assert!(Algorithms::from_environ([b"TXE_ALG=ECDH-ES".as_slice()]).is_none());
+    }
 }
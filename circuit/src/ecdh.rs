@@ -13,3 +13,55 @@ pub fn shared_secret(private_key: [u8; 32], public_key: [u8; 32]) -> [u8; 32] {
         .mul_clamped(private_key)
         .to_bytes()
 }
+
+/// Validates that a public key is a point on Curve25519 that is not of small
+/// order, so that the shared secret derived from it is not predictable.
+pub fn validate_public_key(public_key: [u8; 32]) -> Result<(), EcdhError> {
+    let point = MontgomeryPoint(public_key)
+        .to_edwards(0)
+        .ok_or(EcdhError::NotOnCurve)?;
+    if point.is_small_order() {
+        return Err(EcdhError::SmallOrder);
+    }
+    Ok(())
+}
+
+/// An invalid ECDH public key.
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum EcdhError {
+    /// The public key is not a point on the curve.
+    NotOnCurve,
+    /// The public key is a point of small order.
+    SmallOrder,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_public_key() {
+        assert!(validate_public_key(public_key([0x11; 32])).is_ok());
+
+        // The identity is encoded as zero in the Montgomery form.
+        assert!(matches!(
+            validate_public_key([0; 32]),
+            Err(EcdhError::SmallOrder),
+        ));
+        // A point of order 8.
+        assert!(matches!(
+            validate_public_key(
+                *b"\xe0\xeb\x7a\x7c\x3b\x41\xb8\xae\x16\x56\xe3\xfa\xf1\x9f\xc4\x6a\
+                   \xda\x09\x8d\xeb\x9c\x32\xb1\xfd\x86\x62\x05\x16\x5f\x49\xb8\x00"
+            ),
+            Err(EcdhError::SmallOrder),
+        ));
+        // The point with u = 2 is on the quadratic twist.
+        let mut twist = [0; 32];
+        twist[0] = 2;
+        assert!(matches!(
+            validate_public_key(twist),
+            Err(EcdhError::NotOnCurve),
+        ));
+    }
+}
@@ -1,41 +1,166 @@
 //! Encryption implementation.
+use crate::{ContentAlg, Zeroizing, base64url};
 use aes_gcm::{
     Aes128Gcm, Key, Nonce,
     aead::{AeadMutInPlace as _, KeyInit as _},
 };
 use aes_kw::KekAes128;
+use hkdf::Hkdf;
 use sha2::{Digest as _, Sha256};
+use std::borrow::Cow;
+
+/// The static additional authenticated data used in the Safe TXE format.
+/// This is the base64url encoding of {"enc":"A128GCM"} without padding.
+pub const AAD: &[u8] = br#"eyJlbmMiOiJBMTI4R0NNIn0"#;
+
+/// Returns the additional authenticated data for the default `A128GCM`
+/// content encryption algorithm.
+pub fn default_aad() -> &'static [u8] {
+    AAD
+}
+
+/// Returns the additional authenticated data for a content encryption
+/// algorithm, the base64url encoded JWE protected header `{"enc":"<alg>"}`.
+pub fn aad_for(alg: ContentAlg) -> Cow<'static, [u8]> {
+    if alg == ContentAlg::default() {
+        return Cow::Borrowed(default_aad());
+    }
+    let header = format!(r#"{{"enc":"{}"}}"#, alg.name());
+    Cow::Owned(base64url::encode(header.as_bytes()).into_bytes())
+}
 
 /// Content encryption algorithm.
+///
+/// When `bound_nonce` is set, the Safe transaction nonce is additionally bound
+/// to the ciphertext as JWE additional authenticated data, so that a tag
+/// computed for one nonce does not verify for another.
 pub fn content(
     plaintext: &[u8],
     key: [u8; 16],
     iv: [u8; 12],
+    bound_nonce: Option<[u8; 32]>,
+) -> Result<(Vec<u8>, [u8; 16]), aes_gcm::Error> {
+    content_with_aad(plaintext, key, iv, &bound_aad(default_aad(), bound_nonce))
+}
+
+/// Returns the additional authenticated data for the base64url encoded JWE
+/// protected header `protected`, additionally binding the Safe transaction
+/// nonce when `bound_nonce` is set.
+pub fn bound_aad(protected: &[u8], bound_nonce: Option<[u8; 32]>) -> Cow<'_, [u8]> {
+    match bound_nonce {
+        // The JWE AAD is `BASE64URL(protected) || '.' || BASE64URL(aad)`.
+        Some(nonce) => Cow::Owned([protected, b".", base64url::encode(&nonce).as_bytes()].concat()),
+        None => Cow::Borrowed(protected),
+    }
+}
+
+/// Content encryption algorithm with explicit additional authenticated data.
+pub fn content_with_aad(
+    plaintext: &[u8],
+    key: [u8; 16],
+    iv: [u8; 12],
+    aad: &[u8],
 ) -> Result<(Vec<u8>, [u8; 16]), aes_gcm::Error> {
-    // The static additional authenticated data used in the Safe TXE format.
-    // This is the base64url encoding of {"enc":"A128GCM"} without padding.
-    const AAD: &[u8] = br#"eyJlbmMiOiJBMTI4R0NNIn0"#;
+    #[cfg(test)]
+    assert!(
+        !tests::FAIL_CONTENT.get(),
+        "content encryption performed with fault injection enabled",
+    );
 
     let key = Key::<Aes128Gcm>::from(key);
     let iv = Nonce::from(iv);
 
     let mut cipher = Aes128Gcm::new(&key);
     let mut ciphertext = plaintext.to_vec();
-    let tag = cipher.encrypt_in_place_detached(&iv, AAD, &mut ciphertext)?;
+    let tag = cipher.encrypt_in_place_detached(&iv, aad, &mut ciphertext)?;
 
     Ok((ciphertext, tag.into()))
 }
 
+/// Returns the number of 16-byte block operations performed by AES-GCM for a
+/// plaintext and additional authenticated data of the given lengths.
+///
+/// This counts both the AES block encryptions, one per plaintext block plus
+/// one each for the hash subkey and the tag mask, and the GHASH
+/// multiplications, one per additional authenticated data and ciphertext
+/// block plus one for the final lengths block. It is intended for modelling
+/// proving cost, where the block operations dominate.
+pub fn gcm_block_count(plaintext_len: usize, aad_len: usize) -> usize {
+    const BLOCK_LEN: usize = 16;
+    let plaintext_blocks = plaintext_len.div_ceil(BLOCK_LEN);
+    let aad_blocks = aad_len.div_ceil(BLOCK_LEN);
+    let aes_blocks = plaintext_blocks + 2;
+    let ghash_blocks = aad_blocks + plaintext_blocks + 1;
+    aes_blocks + ghash_blocks
+}
+
+/// Content encryption algorithm, without nonce binding, returning the
+/// ciphertext and tag concatenated as `ciphertext || tag`.
+pub fn content_jwe(
+    plaintext: &[u8],
+    key: [u8; 16],
+    iv: [u8; 12],
+) -> Result<Vec<u8>, aes_gcm::Error> {
+    let (mut ciphertext, tag) = content(plaintext, key, iv, None)?;
+    ciphertext.extend_from_slice(&tag);
+    Ok(ciphertext)
+}
+
+/// Content decryption algorithm, without nonce binding.
+pub fn decrypt_content(
+    ciphertext: &[u8],
+    key: [u8; 16],
+    iv: [u8; 12],
+    tag: [u8; 16],
+) -> Result<Vec<u8>, aes_gcm::Error> {
+    decrypt_content_with_aad(ciphertext, key, iv, tag, default_aad())
+}
+
+/// Content decryption algorithm with explicit additional authenticated data.
+pub fn decrypt_content_with_aad(
+    ciphertext: &[u8],
+    key: [u8; 16],
+    iv: [u8; 12],
+    tag: [u8; 16],
+    aad: &[u8],
+) -> Result<Vec<u8>, aes_gcm::Error> {
+    let key = Key::<Aes128Gcm>::from(key);
+    let iv = Nonce::from(iv);
+
+    let mut cipher = Aes128Gcm::new(&key);
+    let mut plaintext = ciphertext.to_vec();
+    cipher.decrypt_in_place_detached(&iv, aad, &mut plaintext, &tag.into())?;
+
+    Ok(plaintext)
+}
+
 /// Content key encryption algorithm.
 pub fn key(key: [u8; 16], shared_secret: [u8; 32]) -> Result<[u8; 24], aes_kw::Error> {
-    let kek = kdf(shared_secret);
+    wrap(key, kdf(shared_secret))
+}
+
+/// Content key wrapping with an already derived key encryption key.
+pub fn wrap(key: [u8; 16], key_encryption_key: [u8; 16]) -> Result<[u8; 24], aes_kw::Error> {
+    let kek = KekAes128::from(key_encryption_key);
     let mut encrypted_key = [0u8; 24];
     kek.wrap(&key, &mut encrypted_key)?;
     Ok(encrypted_key)
 }
 
+/// Content key decryption algorithm.
+pub fn unwrap_key(
+    encrypted_key: [u8; 24],
+    shared_secret: [u8; 32],
+) -> Result<Zeroizing<[u8; 16]>, aes_kw::Error> {
+    let key_encryption_key = Zeroizing::new(kdf(shared_secret));
+    let kek = KekAes128::from(*key_encryption_key);
+    let mut key = Zeroizing::new([0u8; 16]);
+    kek.unwrap(&encrypted_key, &mut *key)?;
+    Ok(key)
+}
+
 /// Key derivation algorithm from a shared secret to a key encryption key.
-fn kdf<const N: usize>(shared_secret: [u8; N]) -> KekAes128 {
+pub fn kdf<const N: usize>(shared_secret: [u8; N]) -> [u8; 16] {
     // Concat KDF algorithm from NIST SP 800-56A, with fixed parameters for
     // a 128-bit key, the SHA-256 as the hash function, and "other info".
     const KEY_LEN: usize = 16;
@@ -55,6 +180,218 @@ fn kdf<const N: usize>(shared_secret: [u8; N]) -> KekAes128 {
         unsafe { *dk.get_unchecked_mut(i) = digest };
     }
 
-    let k = unsafe { dk.as_ptr().cast::<[u8; KEY_LEN]>().read() };
-    KekAes128::from(k)
+    unsafe { dk.as_ptr().cast::<[u8; KEY_LEN]>().read() }
+}
+
+/// Derives a content encryption key from a master secret using HKDF-SHA256
+/// from RFC 5869, without salt and with `context` as the info.
+///
+/// This allows regenerating a key rather than storing it. The master secret
+/// must be high-entropy (such as 32 random bytes); HKDF does not protect
+/// low-entropy secrets like passwords from brute-forcing. The returned key is
+/// wiped when dropped.
+pub fn derive_cek(master: &[u8], context: &[u8]) -> Zeroizing<[u8; 16]> {
+    let mut key = Zeroizing::new([0; 16]);
+    Hkdf::<Sha256>::new(None, master)
+        .expand(context, &mut *key)
+        .expect("16 bytes is a valid HKDF-SHA256 output length");
+    key
+}
+
+/// Content decryption algorithm, without nonce binding, for a ciphertext
+/// with its trailing tag as `ciphertext || tag`.
+pub fn decrypt_content_jwe(
+    ciphertext_and_tag: &[u8],
+    key: [u8; 16],
+    iv: [u8; 12],
+) -> Result<Vec<u8>, aes_gcm::Error> {
+    let (ciphertext, tag) = ciphertext_and_tag
+        .split_last_chunk()
+        .ok_or(aes_gcm::Error)?;
+    decrypt_content(ciphertext, key, iv, *tag)
+}
+
+/// Runs known-answer tests through the content encryption, key wrapping, key
+/// derivation and ECDH primitives, returning an error if any of them produces
+/// an unexpected result, for example because of a miscompiled backend.
+#[cfg(any(test, feature = "self-test"))]
+pub fn self_test() -> Result<(), SelfTestError> {
+    self_test_with(&Primitives {
+        content,
+        key,
+        kdf,
+        shared_secret: crate::ecdh::shared_secret,
+    })
+}
+
+/// A primitive that failed its self-test.
+#[cfg(any(test, feature = "self-test"))]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum SelfTestError {
+    /// Content encryption.
+    Content,
+    /// Content key encryption.
+    Key,
+    /// Key derivation.
+    Kdf,
+    /// ECDH shared secret computation.
+    SharedSecret,
+}
+
+#[cfg(any(test, feature = "self-test"))]
+#[allow(clippy::type_complexity)]
+struct Primitives {
+    content: fn(
+        &[u8],
+        [u8; 16],
+        [u8; 12],
+        Option<[u8; 32]>,
+    ) -> Result<(Vec<u8>, [u8; 16]), aes_gcm::Error>,
+    key: fn([u8; 16], [u8; 32]) -> Result<[u8; 24], aes_kw::Error>,
+    kdf: fn([u8; 32]) -> [u8; 16],
+    shared_secret: fn([u8; 32], [u8; 32]) -> [u8; 32],
+}
+
+#[cfg(any(test, feature = "self-test"))]
+fn self_test_with(primitives: &Primitives) -> Result<(), SelfTestError> {
+    use crate::{kat, shims::BoolExt as _};
+
+    let kat = kat::content();
+    let (ciphertext, tag) = (primitives.content)(kat.plaintext, kat.key, kat.iv, None)
+        .map_err(|_| SelfTestError::Content)?;
+    (ciphertext == kat.ciphertext && tag == kat.tag).xok_or(SelfTestError::Content)?;
+
+    let kat = kat::key();
+    let encrypted_key =
+        (primitives.key)(kat.key, kat.shared_secret).map_err(|_| SelfTestError::Key)?;
+    (encrypted_key == kat.encrypted_key).xok_or(SelfTestError::Key)?;
+
+    let kat = kat::kdf();
+    ((primitives.kdf)(kat.shared_secret) == kat.key_encryption_key).xok_or(SelfTestError::Kdf)?;
+
+    let kat = kat::shared_secret();
+    ((primitives.shared_secret)(kat.private_key, kat.public_key) == kat.shared_secret)
+        .xok_or(SelfTestError::SharedSecret)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        /// Fault injection for content encryption, which panics when enabled
+        /// on the current thread.
+        pub(crate) static FAIL_CONTENT: Cell<bool> = const { Cell::new(false) };
+    }
+
+    #[test]
+    fn test_aad() {
+        assert_eq!(default_aad(), AAD);
+        assert_eq!(*aad_for(ContentAlg::A128Gcm), *AAD);
+        assert_eq!(
+            base64url::encode(br#"{"enc":"A128GCM"}"#).as_bytes(),
+            default_aad(),
+        );
+    }
+
+    #[test]
+    fn test_self_test() {
+        assert!(self_test().is_ok());
+
+        let primitives = || Primitives {
+            content,
+            key,
+            kdf,
+            shared_secret: crate::ecdh::shared_secret,
+        };
+        assert!(matches!(
+            self_test_with(&Primitives {
+                content: |plaintext, key, iv, _| content(plaintext, key, iv, Some([0; 32])),
+                ..primitives()
+            }),
+            Err(SelfTestError::Content),
+        ));
+        assert!(matches!(
+            self_test_with(&Primitives {
+                key: |key, _| wrap(key, [0; 16]),
+                ..primitives()
+            }),
+            Err(SelfTestError::Key),
+        ));
+        assert!(matches!(
+            self_test_with(&Primitives {
+                kdf: |shared_secret| kdf(shared_secret.map(|b| !b)),
+                ..primitives()
+            }),
+            Err(SelfTestError::Kdf),
+        ));
+        assert!(matches!(
+            self_test_with(&Primitives {
+                shared_secret: |_, public_key| public_key,
+                ..primitives()
+            }),
+            Err(SelfTestError::SharedSecret),
+        ));
+    }
+
+    #[test]
+    fn test_derive_cek() {
+        // RFC 5869, appendix A.3 (empty salt and info), truncated to 16 bytes.
+        assert_eq!(
+            *derive_cek(&[0x0b; 22], b""),
+            *b"\x8d\xa4\xe7\x75\xa5\x63\xc1\x8f\x71\x5f\x80\x2a\x06\x3c\x5a\x31",
+        );
+
+        let master = [0x42; 32];
+        assert_eq!(derive_cek(&master, b"tx-1"), derive_cek(&master, b"tx-1"));
+        assert_ne!(derive_cek(&master, b"tx-1"), derive_cek(&master, b"tx-2"));
+        assert_ne!(
+            derive_cek(&master, b"tx-1"),
+            derive_cek(&[0x43; 32], b"tx-1")
+        );
+    }
+
+    #[test]
+    fn test_gcm_block_count() {
+        // The sampled ciphertext is 75 bytes, or 5 blocks, and the AAD is 23
+        // bytes, or 2 blocks: 5 + 2 AES blocks and 2 + 5 + 1 GHASH blocks.
+        let ciphertext_len = crate::tests::input().public.ciphertext.len();
+        assert_eq!(ciphertext_len, 75);
+        assert_eq!(gcm_block_count(ciphertext_len, AAD.len()), 15);
+
+        assert_eq!(gcm_block_count(0, 0), 3);
+        assert_eq!(gcm_block_count(16, 16), 6);
+        assert_eq!(gcm_block_count(17, 0), 7);
+    }
+
+    #[test]
+    fn test_content_jwe() {
+        let (key, iv) = ([0x42; 16], [0x17; 12]);
+        let (ciphertext, tag) = content(b"hello", key, iv, None).unwrap();
+        let concatenated = content_jwe(b"hello", key, iv).unwrap();
+
+        let (jwe_ciphertext, jwe_tag) = concatenated.split_last_chunk::<16>().unwrap();
+        assert_eq!(jwe_ciphertext, ciphertext);
+        assert_eq!(*jwe_tag, tag);
+        assert_eq!(
+            decrypt_content_jwe(&concatenated, key, iv).unwrap(),
+            b"hello"
+        );
+        assert!(decrypt_content_jwe(&concatenated[1..], key, iv).is_err());
+        assert!(decrypt_content_jwe(&[0; 15], key, iv).is_err());
+    }
+
+    #[test]
+    fn test_content_bound_nonce() {
+        let (key, iv) = ([0x42; 16], [0x17; 12]);
+        let (ciphertext, tag) = content(b"hello", key, iv, None).unwrap();
+        let (bound_ciphertext, bound_tag) = content(b"hello", key, iv, Some([0; 32])).unwrap();
+        let (other_ciphertext, other_tag) = content(b"hello", key, iv, Some([1; 32])).unwrap();
+
+        assert_eq!(ciphertext, bound_ciphertext);
+        assert_eq!(ciphertext, other_ciphertext);
+        assert_ne!(tag, bound_tag);
+        assert_ne!(bound_tag, other_tag);
+    }
 }
@@ -1,18 +1,43 @@
-//! Hexadecimal string decoding.
+//! Hexadecimal string encoding and decoding.
 
-/// Decodes a hexadecimal string into bytes.
-pub fn decode(s: &str) -> Result<Vec<u8>, Error> {
-    let hex = s.strip_prefix("0x").ok_or(Error)?;
-    let (bytes, rest) = hex.as_bytes().as_chunks::<2>();
-    if !rest.is_empty() {
-        return Err(Error);
+use std::{error, fmt};
+
+/// Encodes bytes into a `0x`-prefixed hexadecimal string.
+pub fn encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("0x");
+    for &b in bytes {
+        hex.push(DIGITS[(b >> 4) as usize] as char);
+        hex.push(DIGITS[(b & 0xf) as usize] as char);
     }
-    bytes
+    hex
+}
+
+/// Decodes a `0x`-prefixed hexadecimal string into bytes. Both the prefix and
+/// the digits are case-insensitive.
+pub fn decode(s: &str) -> Result<Vec<u8>, Error> {
+    digits(s)?
         .iter()
         .map(|&[hi, lo]| Ok((nibble(hi)? << 4) | nibble(lo)?))
         .collect()
 }
 
+/// Returns the number of bytes a `0x`-prefixed hexadecimal string decodes
+/// to, validating the prefix and length but not the digits themselves.
+pub fn decoded_len(s: &str) -> Result<usize, Error> {
+    Ok(digits(s)?.len())
+}
+
+fn digits(s: &str) -> Result<&[[u8; 2]], Error> {
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .ok_or(Error)?;
+    let (digits, rest) = hex.as_bytes().as_chunks::<2>();
+    rest.is_empty().then_some(digits).ok_or(Error)
+}
+
 fn nibble(b: u8) -> Result<u8, Error> {
     match b {
         b'0'..=b'9' => Ok(b.wrapping_sub(b'0')),
@@ -22,5 +47,46 @@ fn nibble(b: u8) -> Result<u8, Error> {
     }
 }
 
-#[cfg_attr(debug_assertions, derive(Debug))]
+/// A hexadecimal decoding error.
+#[derive(Debug)]
 pub struct Error;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid 0x-prefixed hexadecimal string")
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error() {
+        let err: Box<dyn error::Error> = decode("0xf").unwrap_err().into();
+        assert_eq!(err.to_string(), "invalid 0x-prefixed hexadecimal string");
+    }
+
+    #[test]
+    fn test_decode_prefix() {
+        assert_eq!(decode("0xabCD").unwrap(), [0xab, 0xcd]);
+        assert_eq!(decode("0XABcd").unwrap(), [0xab, 0xcd]);
+        assert!(decode("0X").unwrap().is_empty());
+        assert!(decode("abcd").is_err());
+        assert!(decode("").is_err());
+        assert!(decode("x0abcd").is_err());
+    }
+
+    #[test]
+    fn test_decoded_len() {
+        assert_eq!(decoded_len("0x").unwrap(), 0);
+        assert_eq!(decoded_len("0xabcd").unwrap(), 2);
+        assert_eq!(decoded_len("0Xabcd").unwrap(), 2);
+        assert_eq!(decoded_len("0xzzzz").unwrap(), 2);
+        assert!(decoded_len("0xabc").is_err());
+        assert!(decoded_len("abcd").is_err());
+        assert!(decoded_len("").is_err());
+    }
+}
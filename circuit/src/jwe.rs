@@ -0,0 +1,428 @@
+//! JSON Web Encryption (JWE) interoperability.
+
+use crate::{
+    PublicInput, PublicRecipient, base64url, encrypt, hex, safe::SafeTransaction,
+    shims::BoolExt as _,
+};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, error, fmt};
+
+/// Parses an X25519 public key from an OKP JSON Web Key.
+///
+/// The parsed key can be passed to [`rewrap`](crate::rewrap) to wrap the
+/// content encryption key for the recipient.
+pub fn parse_okp_jwk(json: &str) -> Result<[u8; 32], Error> {
+    #[derive(Deserialize)]
+    struct Jwk<'a> {
+        #[serde(borrow)]
+        kty: Cow<'a, str>,
+        #[serde(borrow)]
+        crv: Option<Cow<'a, str>>,
+        #[serde(borrow)]
+        x: Option<Cow<'a, str>>,
+    }
+
+    let jwk = serde_json::from_str::<Jwk>(json).map_err(|_| Error::InvalidJson)?;
+    (jwk.kty == "OKP").xok_or(Error::UnsupportedKeyType)?;
+    (jwk.crv.as_deref() == Some("X25519")).xok_or(Error::UnsupportedCurve)?;
+    let x = jwk.x.ok_or(Error::InvalidKey)?;
+    base64url::decode(&x)
+        .map_err(|_| Error::InvalidKey)?
+        .try_into()
+        .map_err(|_| Error::InvalidKey)
+}
+
+/// Decrypts a Safe TXE in JWE JSON serialization with a recipient's private
+/// key, and verifies that it contains the claimed Safe transaction which
+/// hashes to the committed struct hash for `nonce`.
+pub fn verify_jwe_matches_transaction(
+    jwe: &str,
+    private_key: [u8; 32],
+    transaction: &SafeTransaction,
+    nonce: [u8; 32],
+    struct_hash: [u8; 32],
+) -> Result<(), AuditError> {
+    let jwe = serde_json::from_str::<Jwe>(jwe).map_err(|_| AuditError::InvalidJwe)?;
+    (jwe.protected.as_bytes() == encrypt::AAD).xok_or(AuditError::UnsupportedAlgorithm)?;
+
+    let content_encryption_key = jwe
+        .public_recipients()?
+        .iter()
+        .find_map(|recipient| crate::unwrap_key(recipient, private_key).ok())
+        .ok_or(AuditError::NoMatchingRecipient)?;
+
+    let mut ciphertext =
+        vec![0; base64url::decoded_len(jwe.ciphertext.len()).ok_or(AuditError::InvalidJwe)?];
+    base64url::decode_into(&jwe.ciphertext, &mut ciphertext).map_err(|_| AuditError::InvalidJwe)?;
+    let plaintext = encrypt::decrypt_content(
+        &ciphertext,
+        *content_encryption_key,
+        field(&jwe.iv)?,
+        field(&jwe.tag)?,
+    )
+    .map_err(|_| AuditError::DecryptionFailure)?;
+
+    let decrypted =
+        SafeTransaction::decode(&plaintext).map_err(|_| AuditError::InvalidTransaction)?;
+    (decrypted == *transaction).xok_or(AuditError::TransactionMismatch)?;
+    (decrypted.struct_hash(nonce) == struct_hash).xok_or(AuditError::StructHashMismatch)?;
+
+    Ok(())
+}
+
+/// Serializes a public input as a Safe TXE in JWE general JSON serialization.
+///
+/// The protected header, IV, ciphertext and tag are shared by all recipients,
+/// and each recipient carries its own ephemeral public key and encrypted key.
+/// The Safe transaction nonce and struct hash the ciphertext commits to are
+/// included as hex strings in the shared unprotected header.
+pub fn to_general_json(public: &PublicInput) -> String {
+    let jwe = Jwe {
+        protected: str::from_utf8(encrypt::AAD).unwrap().into(),
+        unprotected: Some(Unprotected {
+            nonce: hex::encode(&public.nonce).into(),
+            struct_hash: hex::encode(&public.struct_hash).into(),
+        }),
+        iv: base64url::encode(&public.iv).into(),
+        ciphertext: base64url::encode(&public.ciphertext).into(),
+        tag: base64url::encode(&public.tag).into(),
+        recipients: public
+            .recipients
+            .iter()
+            .map(|recipient| Recipient {
+                header: Header {
+                    alg: "ECDH-ES+A128KW".into(),
+                    epk: Epk {
+                        kty: "OKP".into(),
+                        crv: "X25519".into(),
+                        x: base64url::encode(&recipient.ephemeral_public_key).into(),
+                    },
+                },
+                encrypted_key: base64url::encode(&recipient.encrypted_key).into(),
+            })
+            .collect(),
+    };
+    serde_json::to_string(&jwe).unwrap()
+}
+
+/// Parses a Safe TXE in JWE general JSON serialization, as produced by
+/// [`to_general_json`], into a public input.
+pub fn parse(json: &str) -> Result<PublicInput<'static>, AuditError> {
+    fn uint(value: &str) -> Result<[u8; 32], AuditError> {
+        hex::decode(value)
+            .map_err(|_| AuditError::InvalidJwe)?
+            .try_into()
+            .map_err(|_| AuditError::InvalidJwe)
+    }
+
+    let jwe = serde_json::from_str::<Jwe>(json).map_err(|_| AuditError::InvalidJwe)?;
+    (jwe.protected.as_bytes() == encrypt::AAD).xok_or(AuditError::UnsupportedAlgorithm)?;
+    let unprotected = jwe.unprotected.as_ref().ok_or(AuditError::InvalidJwe)?;
+    Ok(PublicInput {
+        struct_hash: uint(&unprotected.struct_hash)?,
+        nonce: uint(&unprotected.nonce)?,
+        ciphertext: base64url::decode(&jwe.ciphertext)
+            .map_err(|_| AuditError::InvalidJwe)?
+            .into(),
+        iv: field(&jwe.iv)?,
+        tag: field(&jwe.tag)?,
+        recipients: jwe.public_recipients()?.into(),
+    })
+}
+
+#[derive(Serialize, Deserialize)]
+struct Jwe<'a> {
+    #[serde(borrow)]
+    protected: Cow<'a, str>,
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    unprotected: Option<Unprotected<'a>>,
+    #[serde(borrow)]
+    iv: Cow<'a, str>,
+    #[serde(borrow)]
+    ciphertext: Cow<'a, str>,
+    #[serde(borrow)]
+    tag: Cow<'a, str>,
+    #[serde(borrow)]
+    recipients: Vec<Recipient<'a>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Unprotected<'a> {
+    #[serde(borrow)]
+    nonce: Cow<'a, str>,
+    #[serde(borrow)]
+    struct_hash: Cow<'a, str>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Recipient<'a> {
+    #[serde(borrow)]
+    header: Header<'a>,
+    #[serde(borrow)]
+    encrypted_key: Cow<'a, str>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header<'a> {
+    #[serde(borrow)]
+    alg: Cow<'a, str>,
+    #[serde(borrow)]
+    epk: Epk<'a>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Epk<'a> {
+    #[serde(borrow)]
+    kty: Cow<'a, str>,
+    #[serde(borrow)]
+    crv: Cow<'a, str>,
+    #[serde(borrow)]
+    x: Cow<'a, str>,
+}
+
+impl Jwe<'_> {
+    fn public_recipients(&self) -> Result<Vec<PublicRecipient>, AuditError> {
+        self.recipients
+            .iter()
+            .map(|recipient| {
+                let header = &recipient.header;
+                (header.alg == "ECDH-ES+A128KW"
+                    && header.epk.kty == "OKP"
+                    && header.epk.crv == "X25519")
+                    .xok_or(AuditError::UnsupportedAlgorithm)?;
+                Ok(PublicRecipient {
+                    encrypted_key: field(&recipient.encrypted_key)?,
+                    ephemeral_public_key: field(&header.epk.x)?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn field<const N: usize>(value: &str) -> Result<[u8; N], AuditError> {
+    let mut bytes = [0; N];
+    let len = base64url::decode_into(value, &mut bytes).map_err(|_| AuditError::InvalidJwe)?;
+    (len == N).xok_or(AuditError::InvalidJwe)?;
+    Ok(bytes)
+}
+
+/// A JWE interoperability error.
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid JSON.
+    InvalidJson,
+    /// Unsupported JWK key type.
+    UnsupportedKeyType,
+    /// Unsupported JWK curve.
+    UnsupportedCurve,
+    /// Invalid key material.
+    InvalidKey,
+}
+
+/// An error auditing the contents of a JWE.
+#[derive(Debug)]
+pub enum AuditError {
+    /// Invalid JWE JSON serialization.
+    InvalidJwe,
+    /// The JWE uses algorithms other than `ECDH-ES+A128KW` and `A128GCM`.
+    UnsupportedAlgorithm,
+    /// No recipient's encrypted key can be unwrapped with the private key.
+    NoMatchingRecipient,
+    /// The ciphertext failed to decrypt.
+    DecryptionFailure,
+    /// The plaintext is not an RLP encoded Safe transaction.
+    InvalidTransaction,
+    /// The plaintext is not the claimed Safe transaction.
+    TransactionMismatch,
+    /// The Safe transaction does not hash to the committed struct hash.
+    StructHashMismatch,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidJson => "invalid JWK JSON",
+            Self::UnsupportedKeyType => "unsupported JWK key type",
+            Self::UnsupportedCurve => "unsupported JWK curve",
+            Self::InvalidKey => "invalid JWK key material",
+        })
+    }
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for AuditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidJwe => "invalid JWE JSON serialization",
+            Self::UnsupportedAlgorithm => "unsupported JWE algorithm",
+            Self::NoMatchingRecipient => "no matching JWE recipient",
+            Self::DecryptionFailure => "JWE ciphertext failed to decrypt",
+            Self::InvalidTransaction => "JWE plaintext is not a Safe transaction",
+            Self::TransactionMismatch => "JWE plaintext is not the claimed Safe transaction",
+            Self::StructHashMismatch => "Safe transaction does not match the struct hash",
+        })
+    }
+}
+
+impl error::Error for AuditError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ecdh, rewrap, tests::input};
+
+    fn jwe(private_key: [u8; 32]) -> String {
+        let input = input();
+        let mut ephemeral_private_keys = [[0x33; 32], [0x44; 32]].into_iter();
+        let Ok(public) = rewrap(
+            &input.public,
+            input.private.content_encryption_key,
+            &[ecdh::public_key([0x22; 32]), ecdh::public_key(private_key)],
+            || ephemeral_private_keys.next().unwrap(),
+        ) else {
+            panic!("rewrap failed");
+        };
+
+        let recipients = public
+            .recipients
+            .iter()
+            .map(|recipient| {
+                format!(
+                    r#"{{"encrypted_key":"{}","header":{{"alg":"ECDH-ES+A128KW",
+                        "epk":{{"x":"{}","crv":"X25519","kty":"OKP"}}}}}}"#,
+                    base64url::encode(&recipient.encrypted_key),
+                    base64url::encode(&recipient.ephemeral_public_key),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            r#"{{"ciphertext":"{}","iv":"{}","tag":"{}","recipients":[{}],
+                "protected":"eyJlbmMiOiJBMTI4R0NNIn0"}}"#,
+            base64url::encode(&public.ciphertext),
+            base64url::encode(&public.iv),
+            base64url::encode(&public.tag),
+            recipients,
+        )
+    }
+
+    #[test]
+    fn test_verify_jwe_matches_transaction() {
+        let input = input();
+        let jwe = jwe([0x11; 32]);
+        let mut transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+        assert!(
+            verify_jwe_matches_transaction(
+                &jwe,
+                [0x11; 32],
+                &transaction,
+                input.public.nonce,
+                input.public.struct_hash,
+            )
+            .is_ok()
+        );
+
+        assert!(matches!(
+            verify_jwe_matches_transaction(
+                &jwe,
+                [0x55; 32],
+                &transaction,
+                input.public.nonce,
+                input.public.struct_hash,
+            ),
+            Err(AuditError::NoMatchingRecipient),
+        ));
+        assert!(matches!(
+            verify_jwe_matches_transaction(
+                &jwe,
+                [0x11; 32],
+                &transaction,
+                [0; 32],
+                input.public.struct_hash,
+            ),
+            Err(AuditError::StructHashMismatch),
+        ));
+
+        transaction.value[31] ^= 1;
+        assert!(matches!(
+            verify_jwe_matches_transaction(
+                &jwe,
+                [0x11; 32],
+                &transaction,
+                input.public.nonce,
+                input.public.struct_hash,
+            ),
+            Err(AuditError::TransactionMismatch),
+        ));
+    }
+
+    #[test]
+    fn test_general_json() {
+        let public = input().public;
+        let json = to_general_json(&public);
+        assert!(matches!(parse(&json), Ok(parsed) if parsed == public));
+
+        let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(value["protected"], "eyJlbmMiOiJBMTI4R0NNIn0");
+        assert_eq!(
+            value["recipients"].as_array().unwrap().len(),
+            public.recipients.len(),
+        );
+        assert_eq!(value["recipients"][0]["header"]["epk"]["crv"], "X25519");
+
+        assert!(matches!(
+            parse(&json.replace("eyJlbmMiOiJBMTI4R0NNIn0", "eyJlbmMiOiJBMjU2R0NNIn0")),
+            Err(AuditError::UnsupportedAlgorithm),
+        ));
+        assert!(matches!(
+            parse(&jwe([0x11; 32])),
+            Err(AuditError::InvalidJwe)
+        ));
+    }
+
+    #[test]
+    fn test_parse_okp_jwk() {
+        // X25519 public key from RFC 7748, section 6.1.
+        let key = parse_okp_jwk(
+            r#"{"kty":"OKP","crv":"X25519","x":"3p7bfXt9wbTTW2HC7OQ1Nz-DQ8hbeGdNrfx-FG-IK08"}"#,
+        );
+        assert!(matches!(
+            key,
+            Ok(key) if key == *b"\xde\x9e\xdb\x7d\x7b\x7d\xc1\xb4\xd3\x5b\x61\xc2\xec\xe4\x35\x37\
+               \x3f\x83\x43\xc8\x5b\x78\x67\x4d\xad\xfc\x7e\x14\x6f\x88\x2b\x4f",
+        ));
+    }
+
+    #[test]
+    fn test_parse_okp_jwk_wrong_curve() {
+        assert!(matches!(
+            parse_okp_jwk(
+                r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#,
+            ),
+            Err(Error::UnsupportedCurve),
+        ));
+        assert!(matches!(
+            parse_okp_jwk(
+                r#"{"kty":"EC","crv":"P-256",
+                    "x":"f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU",
+                    "y":"x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0"}"#,
+            ),
+            Err(Error::UnsupportedKeyType),
+        ));
+    }
+
+    #[test]
+    fn test_parse_okp_jwk_malformed_x() {
+        assert!(matches!(
+            parse_okp_jwk(
+                r#"{"kty":"OKP","crv":"X25519","x":"3p7bfXt9wbTTW2HC7OQ1Nz+DQ8hbeGdNrfx/FG+IK08"}"#,
+            ),
+            Err(Error::InvalidKey),
+        ));
+        assert!(matches!(
+            parse_okp_jwk(r#"{"kty":"OKP","crv":"X25519","x":"3p7bfXt9"}"#),
+            Err(Error::InvalidKey),
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+//! Known-answer test vectors for the encryption primitives.
+//!
+//! Each vector exercises a single layer of the encryption pipeline, so that
+//! implementations can be checked independently of the end-to-end circuit.
+
+/// Known-answer vector for AES-128-GCM content encryption.
+pub struct Content {
+    /// The plaintext (an RLP encoded Safe transaction).
+    pub plaintext: &'static [u8],
+    /// The content encryption key.
+    pub key: [u8; 16],
+    /// The initialization vector.
+    pub iv: [u8; 12],
+    /// The expected ciphertext.
+    pub ciphertext: &'static [u8],
+    /// The expected authentication tag.
+    pub tag: [u8; 16],
+}
+
+/// Known-answer vector for AES-128 key wrapping of a content encryption key.
+pub struct Key {
+    /// The content encryption key to wrap.
+    pub key: [u8; 16],
+    /// The ECDH shared secret the key encryption key is derived from.
+    pub shared_secret: [u8; 32],
+    /// The expected encrypted key.
+    pub encrypted_key: [u8; 24],
+}
+
+/// Known-answer vector for the Concat KDF.
+pub struct Kdf {
+    /// The ECDH shared secret.
+    pub shared_secret: [u8; 32],
+    /// The expected key encryption key.
+    pub key_encryption_key: [u8; 16],
+}
+
+/// Known-answer vector for X25519 shared secret computation.
+pub struct SharedSecret {
+    /// The private key.
+    pub private_key: [u8; 32],
+    /// The peer's public key.
+    pub public_key: [u8; 32],
+    /// The expected shared secret.
+    pub shared_secret: [u8; 32],
+}
+
+/// Returns the content encryption known-answer vector.
+pub fn content() -> Content {
+    Content {
+        plaintext: b"\xf8\x49\x94\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\
+                     \xa1\xa1\xa1\xa1\xa1\xa1\xa1\x02\x84\x03\x04\x05\x06\x01\x07\x08\
+                     \x09\x94\xa2\xa2\xa2\xa2\xa2\xa2\xa2\xa2\xa2\xa2\xa2\xa2\xa2\xa2\
+                     \xa2\xa2\xa2\xa2\xa2\xa2\x94\xa3\xa3\xa3\xa3\xa3\xa3\xa3\xa3\xa3\
+                     \xa3\xa3\xa3\xa3\xa3\xa3\xa3\xa3\xa3\xa3\xa3",
+        key: *b"\xc3\xba\x3d\x49\xdd\x84\xaa\xf3\x9f\x49\x47\x83\x24\xbc\x31\x69",
+        iv: *b"\xb0\x5c\x98\x41\x65\xf2\xd2\x3a\x28\x00\x0d\x4b",
+        ciphertext: b"\xbf\x39\x26\x1d\x44\x91\x66\x17\xd8\x53\xe3\x53\x8b\x2a\x09\x6f\
+                      \xfd\x7c\xe3\x23\x62\x10\xe6\x13\xed\x4d\xec\xca\x6e\x32\xe4\x69\
+                      \x6c\x4f\x8c\x24\x73\x4c\xce\x38\xa1\xce\x3a\x15\x00\xf7\x4f\x58\
+                      \xb5\x75\x18\x8b\x33\xd4\xe8\xed\x89\x61\xaa\x9f\x0f\x64\x07\xdb\
+                      \x78\x8e\x7f\x1f\xd5\xaf\x28\xdb\x60\x01\xfb",
+        tag: *b"\x08\xe6\x7b\x91\xdc\xd3\x8c\x7a\x1f\x48\xb9\x3b\x59\xff\xe1\xb8",
+    }
+}
+
+/// Returns the content key encryption known-answer vector.
+pub fn key() -> Key {
+    Key {
+        key: *b"\xc3\xba\x3d\x49\xdd\x84\xaa\xf3\x9f\x49\x47\x83\x24\xbc\x31\x69",
+        shared_secret: *b"\xbb\xe5\x71\x19\xd1\x74\xed\xd2\x78\x7f\xb3\x02\xac\x4b\xa8\xbc\
+                           \x46\x52\xbe\x3f\x10\x06\x9d\xcc\x47\x70\x39\xe4\xb9\x6a\xf9\x33",
+        encrypted_key: *b"\x59\x0a\x3a\x98\xe5\x8d\xad\xf5\x22\xba\xa9\x13\x57\xec\x1d\x0f\
+                           \x4f\x53\x05\xc6\xdd\x88\x57\x45",
+    }
+}
+
+/// Returns the key derivation known-answer vector.
+pub fn kdf() -> Kdf {
+    Kdf {
+        shared_secret: *b"\xbb\xe5\x71\x19\xd1\x74\xed\xd2\x78\x7f\xb3\x02\xac\x4b\xa8\xbc\
+                           \x46\x52\xbe\x3f\x10\x06\x9d\xcc\x47\x70\x39\xe4\xb9\x6a\xf9\x33",
+        key_encryption_key: *b"\x82\x2c\x13\x60\x57\x59\x71\x8d\xbb\x6f\x38\x5a\x90\x07\x88\x88",
+    }
+}
+
+/// Returns the X25519 shared secret known-answer vector from RFC 7748,
+/// section 6.1.
+pub fn shared_secret() -> SharedSecret {
+    SharedSecret {
+        private_key: *b"\x77\x07\x6d\x0a\x73\x18\xa5\x7d\x3c\x16\xc1\x72\x51\xb2\x66\x45\
+                        \xdf\x4c\x2f\x87\xeb\xc0\x99\x2a\xb1\x77\xfb\xa5\x1d\xb9\x2c\x2a",
+        public_key: *b"\xde\x9e\xdb\x7d\x7b\x7d\xc1\xb4\xd3\x5b\x61\xc2\xec\xe4\x35\x37\
+                       \x3f\x83\x43\xc8\x5b\x78\x67\x4d\xad\xfc\x7e\x14\x6f\x88\x2b\x4f",
+        shared_secret: *b"\x4a\x5d\x9d\x5b\xa4\xce\x2d\xe1\x72\x8e\x3b\xf4\x80\x35\x0f\x25\
+                           \xe0\x7e\x21\xc9\x47\xd1\x9e\x33\x76\xf0\x9b\x3c\x1e\x16\x17\x42",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ecdh, encrypt};
+
+    #[test]
+    fn test_content() {
+        let kat = content();
+        let (ciphertext, tag) = encrypt::content(kat.plaintext, kat.key, kat.iv, None).unwrap();
+        assert_eq!(ciphertext, kat.ciphertext);
+        assert_eq!(tag, kat.tag);
+    }
+
+    #[test]
+    fn test_key() {
+        let kat = key();
+        let encrypted_key = encrypt::key(kat.key, kat.shared_secret).unwrap();
+        assert_eq!(encrypted_key, kat.encrypted_key);
+    }
+
+    #[test]
+    fn test_kdf() {
+        let kat = kdf();
+        assert_eq!(encrypt::kdf(kat.shared_secret), kat.key_encryption_key);
+    }
+
+    #[test]
+    fn test_shared_secret() {
+        let kat = shared_secret();
+        assert_eq!(
+            ecdh::shared_secret(kat.private_key, kat.public_key),
+            kat.shared_secret,
+        );
+    }
+}
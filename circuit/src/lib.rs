@@ -1,15 +1,49 @@
+#[cfg(feature = "abi")]
+pub mod abi;
+pub mod base64url;
 pub mod capi;
 mod ecdh;
-mod encrypt;
-mod hex;
-mod rlp;
-mod safe;
+pub mod encrypt;
+pub mod hex;
+#[cfg(feature = "jwe")]
+pub mod jwe;
+#[cfg(any(test, feature = "kat"))]
+pub mod kat;
+mod profile;
+pub mod rlp;
+pub mod safe;
 mod shims;
+pub mod u256;
 
-use crate::{safe::SafeTransaction, shims::BoolExt as _};
-use std::{borrow::Cow, iter};
+use crate::{
+    safe::{Operation, SafeTransaction},
+    shims::BoolExt as _,
+};
+use sha3::{Digest as _, Keccak256};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    error, fmt,
+    io::{self, BufRead, Read},
+    iter,
+    str::FromStr,
+};
+pub use zeroize::Zeroizing;
+
+/// Internals re-exported for the benchmarks. Not part of the public API.
+#[doc(hidden)]
+pub mod bench {
+    /// Elliptic Curve Diffie-Hellman (ECDH) key exchange.
+    pub mod ecdh {
+        pub use crate::ecdh::{public_key, shared_secret};
+    }
+}
+
+/// The current input envelope format version.
+pub const ENVELOPE_VERSION: u8 = 1;
 
 /// The input to the circuit.
+#[derive(Clone, PartialEq, Eq)]
 pub struct Input<'a> {
     /// The public input.
     pub public: PublicInput<'a>,
@@ -17,7 +51,242 @@ pub struct Input<'a> {
     pub private: PrivateInput<'a>,
 }
 
+impl Input<'_> {
+    /// Moves the recipient entries to the private side, replacing them in the
+    /// public input with the commitment to the recipient entries.
+    pub fn commit_recipients(&self) -> CommittedInput<'_> {
+        let public = &self.public;
+        CommittedInput {
+            public: CommittedPublicInput {
+                struct_hash: public.struct_hash,
+                nonce: public.nonce,
+                ciphertext: Cow::Borrowed(&public.ciphertext),
+                iv: public.iv,
+                tag: public.tag,
+                recipients_commitment: recipients_commitment(
+                    &public.recipients,
+                    &self.private.recipients,
+                ),
+            },
+            private: PrivateInput {
+                transaction: Cow::Borrowed(&self.private.transaction),
+                content_encryption_key: self.private.content_encryption_key,
+                recipients: Cow::Borrowed(&self.private.recipients),
+            },
+            recipients: Cow::Borrowed(&public.recipients),
+        }
+    }
+
+    /// RLP-decodes a combined `[public, private]` input.
+    pub fn decode(encoded: &[u8]) -> Result<Input<'static>, rlp::Error> {
+        Input::try_from(encoded).map(Input::into_owned)
+    }
+
+    /// RLP-decodes a combined `[public, private]` input with recipient key
+    /// material in the given encoding.
+    pub fn decode_with(
+        encoded: &[u8],
+        key_encoding: KeyEncoding,
+    ) -> Result<Input<'static>, DecodeError> {
+        Input::decode_combined(encoded, key_encoding).map(Input::into_owned)
+    }
+
+    fn decode_combined<K: KeyDecoder>(encoded: &[u8], keys: K) -> Result<Input<'_>, K::Error> {
+        rlp::Decoder::new(encoded).decode_struct(move |decoder| {
+            let mut list = decoder.list()?;
+            let public = PublicInput::decode_fields_with(&mut list, keys)?;
+            list.done()?;
+            let mut list = decoder.list()?;
+            let private = PrivateInput::decode_fields_with(&mut list, keys)?;
+            list.done()?;
+            Ok(Input { public, private })
+        })
+    }
+
+    /// RLP-decodes separately encoded public and private inputs, borrowing the
+    /// ciphertext and transaction from the encoded buffers instead of copying
+    /// them.
+    pub fn decode_borrowed<'a>(
+        public: &'a [u8],
+        private: &'a [u8],
+    ) -> Result<Input<'a>, rlp::Error> {
+        Ok(Input {
+            public: rlp::Decoder::new(public).decode_struct(PublicInput::decode_fields)?,
+            private: rlp::Decoder::new(private).decode_struct(PrivateInput::decode_fields)?,
+        })
+    }
+
+    /// Decodes a combined input from an envelope, the combined RLP encoding
+    /// prefixed by a format version byte, so that inputs in a future format
+    /// are rejected instead of being mis-parsed.
+    pub fn decode_envelope(envelope: &[u8]) -> Result<Input<'static>, EnvelopeError> {
+        let (&version, encoded) = envelope.split_first().ok_or(EnvelopeError::Truncated)?;
+        match version {
+            ENVELOPE_VERSION => Self::decode(encoded).map_err(EnvelopeError::Rlp),
+            version => Err(EnvelopeError::UnsupportedVersion { version }),
+        }
+    }
+
+    /// Encodes the input as an envelope with the current format version.
+    pub fn encode_envelope(&self) -> Vec<u8> {
+        let mut envelope = vec![ENVELOPE_VERSION];
+        envelope.extend_from_slice(&self.encode());
+        envelope
+    }
+
+    /// Reads a length-delimited combined input, framed by its length as a
+    /// 4-byte big-endian integer.
+    pub fn read_framed<R: Read>(r: &mut R) -> io::Result<Input<'static>> {
+        let mut len = [0; 4];
+        r.read_exact(&mut len)?;
+        // Read through `take` rather than allocating the claimed length up
+        // front, so a bogus frame header cannot force a huge allocation.
+        let len = u32::from_be_bytes(len);
+        let mut encoded = Vec::new();
+        r.take(len.into()).read_to_end(&mut encoded)?;
+        (encoded.len() == len as usize).xok_or(io::ErrorKind::UnexpectedEof)?;
+        Self::decode(&encoded).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Reads a combined input from a stream, consuming exactly the bytes of
+    /// its RLP encoding. The encoding is buffered in full before decoding.
+    pub fn read_rlp<R: BufRead>(r: &mut R) -> io::Result<Input<'static>> {
+        let mut reader = rlp::FrameReader::new(r);
+        let encoded = reader.next_frame()?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        Self::decode(encoded).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// RLP-encodes the input as a combined `[public, private]` list.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = rlp::Encoder::new();
+        encoder.list(|list| {
+            list.list(|fields| self.public.encode_fields(fields));
+            list.list(|fields| self.private.encode_fields(fields));
+        });
+        encoder.finish()
+    }
+
+    /// RLP-encodes the input as a combined `[public, private]` list with
+    /// recipient key material in the given encoding.
+    pub fn encode_with(&self, key_encoding: KeyEncoding) -> Vec<u8> {
+        let mut encoder = rlp::Encoder::new();
+        encoder.list(|list| {
+            list.list(|fields| self.public.encode_fields_with(fields, key_encoding));
+            list.list(|fields| self.private.encode_fields_with(fields, key_encoding));
+        });
+        encoder.finish()
+    }
+
+    /// Returns the public and private input as `0x`-prefixed hex strings, in
+    /// the argument format expected by the WASI guest's `_start`.
+    pub fn to_wasi_args(&self) -> [String; 2] {
+        [
+            hex::encode(&self.public.encode()),
+            hex::encode(&self.private.encode()),
+        ]
+    }
+
+    /// Converts into an owned input, cloning any borrowed data.
+    pub fn into_owned(self) -> Input<'static> {
+        Input {
+            public: self.public.into_owned(),
+            private: self.private.into_owned(),
+        }
+    }
+
+    /// Clones into a `'static` input. Unlike [`Clone`], which keeps
+    /// borrowing any borrowed data, the result does not borrow from `self`.
+    pub fn to_static(&self) -> Input<'static> {
+        self.clone().into_owned()
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Input<'a> {
+    type Error = rlp::Error;
+
+    /// RLP-decodes a combined `[public, private]` input, borrowing the
+    /// ciphertext and transaction from `encoded`.
+    fn try_from(encoded: &'a [u8]) -> Result<Self, Self::Error> {
+        Input::decode_combined(encoded, RawKeys)
+    }
+}
+
+impl FromStr for Input<'static> {
+    type Err = ParseInputError;
+
+    /// Parses a `0x`-prefixed hexadecimal combined `[public, private]` input.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoded = hex::decode(s).map_err(ParseInputError::Hex)?;
+        Input::decode(&encoded).map_err(ParseInputError::Rlp)
+    }
+}
+
+/// An error parsing a combined input from a hexadecimal string.
+#[derive(Debug)]
+pub enum ParseInputError {
+    /// The string is not valid `0x`-prefixed hexadecimal.
+    Hex(hex::Error),
+    /// The decoded bytes are not a valid RLP encoded combined input.
+    Rlp(rlp::Error),
+}
+
+impl fmt::Display for ParseInputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Hex(err) => write!(f, "invalid combined input: {err}"),
+            Self::Rlp(err) => write!(f, "invalid combined input: {err}"),
+        }
+    }
+}
+
+impl error::Error for ParseInputError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Hex(err) => Some(err),
+            Self::Rlp(err) => Some(err),
+        }
+    }
+}
+
+/// An error decoding an input with recipient key material in a given
+/// [`KeyEncoding`].
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The input is not valid RLP for the expected structure.
+    Rlp(rlp::Error),
+    /// A curve-tagged key has an unknown or unsupported curve id.
+    UnknownCurve {
+        /// The curve id.
+        id: u8,
+    },
+}
+
+impl From<rlp::Error> for DecodeError {
+    fn from(err: rlp::Error) -> Self {
+        Self::Rlp(err)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Rlp(err) => write!(f, "invalid input: {err}"),
+            Self::UnknownCurve { id } => write!(f, "key has unknown curve id {id}"),
+        }
+    }
+}
+
+impl error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Rlp(err) => Some(err),
+            Self::UnknownCurve { .. } => None,
+        }
+    }
+}
+
 /// The public input to the circuit.
+#[derive(Clone, PartialEq, Eq)]
 pub struct PublicInput<'a> {
     /// The Safe transaction struct hash.
     pub struct_hash: [u8; 32],
@@ -34,8 +303,241 @@ pub struct PublicInput<'a> {
     pub recipients: Cow<'a, [PublicRecipient]>,
 }
 
+impl<'a> PublicInput<'a> {
+    /// Assembles a public input from its parts, checking that each part has
+    /// the expected length and that there is at least one recipient.
+    pub fn from_parts(
+        struct_hash: &[u8],
+        nonce: &[u8],
+        ciphertext: impl Into<Cow<'a, [u8]>>,
+        iv: &[u8],
+        tag: &[u8],
+        recipients: impl Into<Cow<'a, [PublicRecipient]>>,
+    ) -> Result<Self, FromPartsError> {
+        fn part<const N: usize>(
+            field: &'static str,
+            bytes: &[u8],
+        ) -> Result<[u8; N], FromPartsError> {
+            bytes.try_into().map_err(|_| FromPartsError::InvalidLength {
+                field,
+                expected: N,
+                got: bytes.len(),
+            })
+        }
+
+        let ciphertext = ciphertext.into();
+        (!ciphertext.is_empty()).xok_or(FromPartsError::EmptyCiphertext)?;
+        let recipients = recipients.into();
+        (!recipients.is_empty()).xok_or(FromPartsError::NoRecipients)?;
+        Ok(PublicInput {
+            struct_hash: part("struct_hash", struct_hash)?,
+            nonce: part("nonce", nonce)?,
+            ciphertext,
+            iv: part("iv", iv)?,
+            tag: part("tag", tag)?,
+            recipients,
+        })
+    }
+}
+
+impl PublicInput<'_> {
+    /// Converts into an owned public input, cloning any borrowed data.
+    pub fn into_owned(self) -> PublicInput<'static> {
+        PublicInput {
+            struct_hash: self.struct_hash,
+            nonce: self.nonce,
+            ciphertext: Cow::Owned(self.ciphertext.into_owned()),
+            iv: self.iv,
+            tag: self.tag,
+            recipients: Cow::Owned(self.recipients.into_owned()),
+        }
+    }
+
+    /// Checks that the struct hash is the hash of the RLP-encoded transaction
+    /// for the public nonce.
+    ///
+    /// This is the same check the circuit performs, exposed so that producers
+    /// can catch struct hashes computed with the wrong nonce before proving.
+    pub fn self_consistent(&self, transaction: &[u8]) -> Result<(), Error> {
+        let decoded =
+            SafeTransaction::decode(transaction).map_err(|_| Error::InvalidTransaction)?;
+        (decoded.struct_hash(self.nonce) == self.struct_hash).xok_or(Error::StructHashMismatch)
+    }
+
+    /// Returns the number of recipients, saturating at `u32::MAX`.
+    pub fn recipient_count(&self) -> u32 {
+        self.recipients.len().try_into().unwrap_or(u32::MAX)
+    }
+
+    /// Returns a content-addressable identifier for the encrypted payload,
+    /// the Keccak-256 hash of `iv || ciphertext || tag`.
+    pub fn ciphertext_hash(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.iv);
+        hasher.update(&self.ciphertext);
+        hasher.update(self.tag);
+        hasher.finalize().into()
+    }
+
+    /// Returns a commitment binding the encrypted payload to the Safe
+    /// transaction identity, the Keccak-256 hash of
+    /// `struct_hash || ciphertext_hash`.
+    pub fn payload_commitment(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.struct_hash);
+        hasher.update(self.ciphertext_hash());
+        hasher.finalize().into()
+    }
+
+    /// RLP-decodes the public input.
+    pub fn decode(encoded: &[u8]) -> Result<PublicInput<'static>, rlp::Error> {
+        rlp::Decoder::new(encoded)
+            .decode_struct(PublicInput::decode_fields)
+            .map(PublicInput::into_owned)
+    }
+
+    /// RLP-encodes the public input.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = rlp::Encoder::new();
+        encoder.list(|fields| self.encode_fields(fields));
+        encoder.finish()
+    }
+
+    /// Returns the length of the RLP encoding of the public input, without
+    /// encoding it.
+    pub fn encoded_len(&self) -> usize {
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|recipient| {
+                rlp::list_len(
+                    rlp::bytes_len(&recipient.encrypted_key)
+                        + rlp::bytes_len(&recipient.ephemeral_public_key),
+                )
+            })
+            .sum();
+        rlp::list_len(
+            rlp::bytes_len(&self.struct_hash)
+                + rlp::uint_len(&self.nonce)
+                + rlp::bytes_len(&self.ciphertext)
+                + rlp::bytes_len(&self.iv)
+                + rlp::bytes_len(&self.tag)
+                + rlp::list_len(recipients),
+        )
+    }
+
+    fn encode_fields(&self, encoder: &mut rlp::Encoder) {
+        self.encode_fields_with(encoder, KeyEncoding::Raw);
+    }
+
+    fn encode_fields_with(&self, encoder: &mut rlp::Encoder, key_encoding: KeyEncoding) {
+        encoder.bytes(&self.struct_hash);
+        encoder.uint(self.nonce);
+        encoder.bytes(&self.ciphertext);
+        encoder.bytes(&self.iv);
+        encoder.bytes(&self.tag);
+        encoder.list(|list| {
+            for recipient in &*self.recipients {
+                list.list(|fields| {
+                    fields.bytes(&recipient.encrypted_key);
+                    key_encoding.encode(fields, recipient.ephemeral_public_key);
+                });
+            }
+        });
+    }
+
+    fn decode_fields<'a>(decoder: &mut rlp::Decoder<'a>) -> Result<PublicInput<'a>, rlp::Error> {
+        Self::decode_fields_with(decoder, RawKeys)
+    }
+
+    fn decode_fields_with<'a, K: KeyDecoder>(
+        decoder: &mut rlp::Decoder<'a>,
+        keys: K,
+    ) -> Result<PublicInput<'a>, K::Error> {
+        Ok(PublicInput {
+            struct_hash: decoder.bytes_array_fast()?,
+            nonce: decoder.uint()?,
+            ciphertext: decoder.bytes()?.into(),
+            iv: decoder.bytes_array_fast()?,
+            tag: decoder.bytes_array_fast()?,
+            recipients: decoder
+                .vec(move |item| PublicRecipient::decode_with(item, keys))?
+                .into(),
+        })
+    }
+}
+
+/// An error assembling a public input from its parts.
+#[derive(Debug)]
+pub enum FromPartsError {
+    /// A part has an invalid length.
+    InvalidLength {
+        /// The name of the public input field.
+        field: &'static str,
+        /// The expected length in bytes.
+        expected: usize,
+        /// The actual length in bytes.
+        got: usize,
+    },
+    /// The ciphertext is empty.
+    EmptyCiphertext,
+    /// There are no recipients.
+    NoRecipients,
+}
+
+/// An error decoding an input envelope.
+#[derive(Debug)]
+pub enum EnvelopeError {
+    /// The envelope is missing its version byte.
+    Truncated,
+    /// The envelope format version is not supported.
+    UnsupportedVersion {
+        /// The envelope format version.
+        version: u8,
+    },
+    /// The enveloped combined input is not validly encoded.
+    Rlp(rlp::Error),
+}
+
+impl fmt::Display for FromPartsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidLength {
+                field,
+                expected,
+                got,
+            } => write!(f, "{field} is {got} bytes, expected {expected}"),
+            Self::EmptyCiphertext => f.write_str("empty ciphertext"),
+            Self::NoRecipients => f.write_str("no recipients"),
+        }
+    }
+}
+
+impl error::Error for FromPartsError {}
+
+impl fmt::Display for EnvelopeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Truncated => f.write_str("envelope is missing its version"),
+            Self::UnsupportedVersion { version } => {
+                write!(f, "unsupported envelope version {version}")
+            }
+            Self::Rlp(err) => write!(f, "invalid envelope: {err}"),
+        }
+    }
+}
+
+impl error::Error for EnvelopeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Rlp(err) => Some(err),
+            Self::Truncated | Self::UnsupportedVersion { .. } => None,
+        }
+    }
+}
+
 /// Public input per recipient.
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct PublicRecipient {
     /// The encrypted content key for the recipient.
     pub encrypted_key: [u8; 24],
@@ -43,7 +545,110 @@ pub struct PublicRecipient {
     pub ephemeral_public_key: [u8; 32],
 }
 
+impl PublicRecipient {
+    /// RLP-decodes a public recipient.
+    fn decode(item: &mut rlp::Decoder) -> Result<Self, rlp::Error> {
+        Self::decode_with(item, RawKeys)
+    }
+
+    fn decode_with<K: KeyDecoder>(item: &mut rlp::Decoder, keys: K) -> Result<Self, K::Error> {
+        item.decode_struct(move |decoder| {
+            Ok(PublicRecipient {
+                encrypted_key: decoder.bytes_array_fast()?,
+                ephemeral_public_key: keys.decode_key(decoder)?,
+            })
+        })
+    }
+
+    /// RLP-decodes a vector of public recipients into a caller provided slice
+    /// without allocating, returning the number of decoded recipients.
+    pub fn decode_vec_into(
+        decoder: &mut rlp::Decoder,
+        out: &mut [PublicRecipient],
+    ) -> Result<usize, rlp::Error> {
+        decoder.vec_into(out, Self::decode)
+    }
+
+    /// Returns the encrypted content key as a byte slice.
+    pub fn encrypted_key_bytes(&self) -> &[u8] {
+        &self.encrypted_key
+    }
+
+    /// Returns the ephemeral public key as a byte slice.
+    pub fn ephemeral_public_key_bytes(&self) -> &[u8] {
+        &self.ephemeral_public_key
+    }
+
+    /// Returns a short fingerprint identifying the recipient entry, the first
+    /// 8 bytes of `keccak256(ephemeral_public_key || encrypted_key)`.
+    ///
+    /// This is intended as a display handle, for example in URLs or logs, and
+    /// is not collision resistant enough to be used in security decisions.
+    pub fn fingerprint(&self) -> [u8; 8] {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.ephemeral_public_key);
+        hasher.update(self.encrypted_key);
+        let digest: [u8; 32] = hasher.finalize().into();
+        *digest.first_chunk().unwrap()
+    }
+}
+
+/// The input to [`circuit_recipients_commitment`], with the recipient
+/// entries moved to the private side and replaced in the public input by
+/// their commitment.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CommittedInput<'a> {
+    /// The public input.
+    pub public: CommittedPublicInput<'a>,
+    /// The private input.
+    pub private: PrivateInput<'a>,
+    /// The recipient encrypted keys and ephemeral public keys. Should be
+    /// omitted when verifying.
+    pub recipients: Cow<'a, [PublicRecipient]>,
+}
+
+impl CommittedInput<'_> {
+    /// Reassembles the full circuit input, borrowing from this input.
+    fn expand(&self) -> Input<'_> {
+        let public = &self.public;
+        Input {
+            public: PublicInput {
+                struct_hash: public.struct_hash,
+                nonce: public.nonce,
+                ciphertext: Cow::Borrowed(&public.ciphertext),
+                iv: public.iv,
+                tag: public.tag,
+                recipients: Cow::Borrowed(&self.recipients),
+            },
+            private: PrivateInput {
+                transaction: Cow::Borrowed(&self.private.transaction),
+                content_encryption_key: self.private.content_encryption_key,
+                recipients: Cow::Borrowed(&self.private.recipients),
+            },
+        }
+    }
+}
+
+/// The public input to [`circuit_recipients_commitment`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct CommittedPublicInput<'a> {
+    /// The Safe transaction struct hash.
+    pub struct_hash: [u8; 32],
+    /// The Safe transaction nonce.
+    pub nonce: [u8; 32],
+    /// The encrypted Safe transaction.
+    pub ciphertext: Cow<'a, [u8]>,
+    /// The initialization vector used for encryption.
+    pub iv: [u8; 12],
+    /// The authentication tag.
+    pub tag: [u8; 16],
+    /// The commitment to the recipient entries, as computed by
+    /// [`recipients_commitment`].
+    pub recipients_commitment: [u8; 32],
+}
+
 /// The private input to the circuit. Should be omitted when verifying.
+#[derive(Clone, PartialEq, Eq)]
 pub struct PrivateInput<'a> {
     /// The RLP encoded Safe transaction.
     pub transaction: Cow<'a, [u8]>,
@@ -54,8 +659,94 @@ pub struct PrivateInput<'a> {
     pub recipients: Cow<'a, [PrivateRecipient]>,
 }
 
+impl PrivateInput<'_> {
+    /// Converts into an owned private input, cloning any borrowed data.
+    pub fn into_owned(self) -> PrivateInput<'static> {
+        PrivateInput {
+            transaction: Cow::Owned(self.transaction.into_owned()),
+            content_encryption_key: self.content_encryption_key,
+            recipients: Cow::Owned(self.recipients.into_owned()),
+        }
+    }
+
+    /// RLP-decodes the private input.
+    pub fn decode(encoded: &[u8]) -> Result<PrivateInput<'static>, rlp::Error> {
+        rlp::Decoder::new(encoded)
+            .decode_struct(PrivateInput::decode_fields)
+            .map(PrivateInput::into_owned)
+    }
+
+    /// RLP-encodes the private input.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = rlp::Encoder::new();
+        encoder.list(|fields| self.encode_fields(fields));
+        encoder.finish()
+    }
+
+    /// Returns the length of the RLP encoding of the private input, without
+    /// encoding it.
+    pub fn encoded_len(&self) -> usize {
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|recipient| {
+                rlp::list_len(
+                    rlp::bytes_len(&recipient.public_key)
+                        + rlp::bytes_len(&recipient.ephemeral_private_key),
+                )
+            })
+            .sum();
+        rlp::list_len(
+            rlp::bytes_len(&self.transaction)
+                + rlp::bytes_len(&self.content_encryption_key)
+                + rlp::list_len(recipients),
+        )
+    }
+
+    fn encode_fields(&self, encoder: &mut rlp::Encoder) {
+        self.encode_fields_with(encoder, KeyEncoding::Raw);
+    }
+
+    fn encode_fields_with(&self, encoder: &mut rlp::Encoder, key_encoding: KeyEncoding) {
+        encoder.bytes(&self.transaction);
+        encoder.bytes(&self.content_encryption_key);
+        encoder.list(|list| {
+            for recipient in &*self.recipients {
+                list.list(|fields| {
+                    key_encoding.encode(fields, recipient.public_key);
+                    fields.bytes(&recipient.ephemeral_private_key);
+                });
+            }
+        });
+    }
+
+    fn decode_fields<'a>(decoder: &mut rlp::Decoder<'a>) -> Result<PrivateInput<'a>, rlp::Error> {
+        Self::decode_fields_with(decoder, RawKeys)
+    }
+
+    fn decode_fields_with<'a, K: KeyDecoder>(
+        decoder: &mut rlp::Decoder<'a>,
+        keys: K,
+    ) -> Result<PrivateInput<'a>, K::Error> {
+        Ok(PrivateInput {
+            transaction: decoder.bytes()?.into(),
+            content_encryption_key: decoder.bytes_array_fast()?,
+            recipients: decoder
+                .vec(move |item| -> Result<_, K::Error> {
+                    item.decode_struct(move |decoder| {
+                        Ok(PrivateRecipient {
+                            public_key: keys.decode_key(decoder)?,
+                            ephemeral_private_key: decoder.bytes_array_fast()?,
+                        })
+                    })
+                })?
+                .into(),
+        })
+    }
+}
+
 /// Private input per recipient.
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct PrivateRecipient {
     /// The recipient's public key used for encryption.
     pub public_key: [u8; 32],
@@ -63,7 +754,153 @@ pub struct PrivateRecipient {
     pub ephemeral_private_key: [u8; 32],
 }
 
+impl PrivateRecipient {
+    /// Returns the recipient's public key as a byte slice.
+    pub fn public_key_bytes(&self) -> &[u8] {
+        &self.public_key
+    }
+}
+
+/// Content encryption algorithm.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum ContentAlg {
+    /// AES in GCM mode with a 128-bit key.
+    #[default]
+    A128Gcm,
+}
+
+impl ContentAlg {
+    /// Returns the content encryption algorithm with the JWE `enc` name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "A128GCM" => Some(Self::A128Gcm),
+            _ => None,
+        }
+    }
+
+    /// Returns the JWE `enc` name of the content encryption algorithm.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::A128Gcm => "A128GCM",
+        }
+    }
+}
+
+/// Key management algorithm.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum KeyAlg {
+    /// Elliptic Curve Diffie-Hellman Ephemeral Static key agreement with the
+    /// content encryption key wrapped using AES with a 128-bit key.
+    #[default]
+    EcdhEsA128Kw,
+    /// Direct use of a content encryption key shared out-of-band with a
+    /// single trusted recipient, without any key agreement or wrapping.
+    Direct,
+}
+
+impl KeyAlg {
+    /// Returns the key management algorithm with the JWE `alg` name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ECDH-ES+A128KW" => Some(Self::EcdhEsA128Kw),
+            "dir" => Some(Self::Direct),
+            _ => None,
+        }
+    }
+}
+
+/// Elliptic curve of recipient key material.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum Curve {
+    /// Curve25519 in Montgomery form, for X25519 key agreement.
+    #[default]
+    X25519,
+}
+
+impl Curve {
+    /// Returns the curve with the given curve id, if supported.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::X25519),
+            _ => None,
+        }
+    }
+
+    /// Returns the curve id used in curve-tagged key encodings.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::X25519 => 0,
+        }
+    }
+}
+
+/// Encoding of recipient key material in RLP encoded inputs.
+///
+/// The recipient public keys and ephemeral public keys are either encoded as
+/// raw 32-byte keys, or prefixed by a one-byte [`Curve`] id. Since X25519 is
+/// the only supported curve, curve-tagged keys with any other id are rejected
+/// when decoding, and the circuit's key agreement is the same for both.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum KeyEncoding {
+    /// Raw keys without a curve id, the legacy format.
+    #[default]
+    Raw,
+    /// Keys prefixed by their curve id.
+    CurveTagged,
+}
+
+impl KeyEncoding {
+    fn encode(self, encoder: &mut rlp::Encoder, key: [u8; 32]) {
+        match self {
+            Self::Raw => encoder.bytes(&key),
+            Self::CurveTagged => encoder.bytes(&[&[Curve::X25519.id()], key.as_slice()].concat()),
+        }
+    }
+}
+
+/// A decoder of recipient key material.
+trait KeyDecoder: Copy + 'static {
+    /// The error decoding a key.
+    type Error: From<rlp::Error>;
+
+    fn decode_key(self, decoder: &mut rlp::Decoder) -> Result<[u8; 32], Self::Error>;
+}
+
+impl KeyDecoder for KeyEncoding {
+    type Error = DecodeError;
+
+    fn decode_key(self, decoder: &mut rlp::Decoder) -> Result<[u8; 32], DecodeError> {
+        match self {
+            Self::Raw => Ok(RawKeys.decode_key(decoder)?),
+            Self::CurveTagged => {
+                let [id, key @ ..] = decoder.bytes_array_fast::<33>()?;
+                match Curve::from_id(id).ok_or(DecodeError::UnknownCurve { id })? {
+                    Curve::X25519 => Ok(key),
+                }
+            }
+        }
+    }
+}
+
+/// Raw keys, as in [`KeyEncoding::Raw`], which can only fail to decode with
+/// an RLP error.
+#[derive(Clone, Copy)]
+struct RawKeys;
+
+impl KeyDecoder for RawKeys {
+    type Error = rlp::Error;
+
+    fn decode_key(self, decoder: &mut rlp::Decoder) -> Result<[u8; 32], rlp::Error> {
+        decoder.bytes_array_fast()
+    }
+}
+
 /// An error executing the circuit.
+#[derive(PartialEq, Eq)]
 #[cfg_attr(debug_assertions, derive(Debug))]
 pub enum Error {
     /// Invalid Safe transaction encoding.
@@ -84,67 +921,795 @@ pub enum Error {
     KeyEncriptionFailure,
     /// Encrypted key mismatch.
     EncryptedKeyMismatch,
+    /// The struct hash is not in the allowlist.
+    NotAllowlisted,
+    /// Error decrypting content key.
+    KeyDecryptionFailure,
+    /// A recipient index is out of range.
+    RecipientIndexOutOfRange {
+        /// The out of range index.
+        index: usize,
+    },
+    /// The content encryption key is all zeros, indicating a broken random
+    /// number generator.
+    WeakKey,
+    /// The Safe transaction delegatecalls to the zero address.
+    ZeroDelegatecallTarget,
+    /// The Safe transaction hash does not match the expected hash.
+    SafeTxHashMismatch {
+        /// The computed struct hash.
+        struct_hash: [u8; 32],
+        /// The computed Safe transaction hash.
+        safe_tx_hash: [u8; 32],
+    },
+    /// Two recipients share the same ephemeral public key.
+    EphemeralKeyReuse {
+        /// The index of the first recipient.
+        a: usize,
+        /// The index of the second recipient.
+        b: usize,
+    },
+    /// The Safe transaction operation is not allowed by the policy.
+    OperationNotAllowed {
+        /// The disallowed operation.
+        op: Operation,
+    },
+    /// A recipient's ephemeral public key is not on the curve or is of small
+    /// order.
+    InvalidEphemeralKey {
+        /// The index of the recipient.
+        index: usize,
+    },
+    /// The Safe transaction value exceeds the policy limit.
+    ValueExceedsLimit,
+    /// The Safe transaction gas or gas price exceeds the policy limit.
+    GasExceedsLimit,
+    /// The public nonce is outside of the policy range.
+    NonceOutOfRange,
+    /// A recipient entry wraps a different content encryption key than the
+    /// other recipients.
+    RecipientCekMismatch {
+        /// The index of the recipient.
+        index: usize,
+    },
+    /// The recipient entries do not match the recipients commitment.
+    RecipientsCommitmentMismatch,
+    /// The struct hash is all zeros, indicating an uninitialized public input.
+    ZeroStructHash,
+    /// The struct hash and ciphertext do not match the payload commitment.
+    PayloadCommitmentMismatch,
+    /// The transaction is not canonically RLP encoded.
+    NonCanonicalTransaction,
+    /// A recipient public key is not on the curve or is of small order.
+    InvalidPublicKey {
+        /// The index of the recipient.
+        index: usize,
+    },
 }
 
 /// The private input to the verifier program.
+///
+/// This runs both [`circuit_content`] and [`circuit_recipients`], proving that
+/// every recipient can decrypt a Safe transaction matching the struct hash.
 pub fn circuit(input: &Input) -> Result<(), Error> {
-    // Verify the transaction matches the struct hash.
-    let transaction = SafeTransaction::decode(&input.private.transaction)
-        .map_err(|_| Error::InvalidTransaction)?;
-    (transaction.struct_hash(input.public.nonce) == input.public.struct_hash)
-        .xok_or(Error::StructHashMismatch)?;
+    verify_input(input)?;
+    Ok(())
+}
 
-    // Verify the content encryption integrity.
-    let (ciphertext, tag) = encrypt::content(
+/// Runs the same verification as [`circuit`], returning the decoded Safe
+/// transaction on success so that callers do not need to decode it again.
+pub fn circuit_decode(input: &Input) -> Result<SafeTransaction<'static>, Error> {
+    Ok(verify_input(input)?.into_owned())
+}
+
+/// Runs the same verification as [`circuit`], additionally requiring the
+/// transaction to be canonically RLP encoded.
+///
+/// The struct hash only commits to the decoded transaction fields, so
+/// distinct encodings of the same transaction, such as integers with leading
+/// zero bytes, verify against the same struct hash. Use this when the
+/// transaction encoding itself is committed to elsewhere.
+pub fn circuit_canonical(input: &Input) -> Result<(), Error> {
+    let transaction = verify_input(input)?;
+    (transaction.encode() == *input.private.transaction).xok_or(Error::NonCanonicalTransaction)
+}
+
+/// Runs the circuit for the key management algorithm `key_alg`.
+///
+/// With [`KeyAlg::EcdhEsA128Kw`], this is the same as [`circuit`]. With
+/// [`KeyAlg::Direct`], the content encryption key is shared out-of-band with a
+/// single trusted recipient, so the public input carries no recipients and the
+/// ephemeral key and key wrapping checks are skipped entirely. The proof then
+/// only guarantees that the ciphertext is the encryption of a Safe transaction
+/// matching the struct hash under *some* key; it makes no guarantees about who
+/// holds that key, and the verifier must trust the producer to have shared it
+/// with the intended recipient.
+pub fn circuit_key_alg(input: &Input, key_alg: KeyAlg) -> Result<(), Error> {
+    match key_alg {
+        KeyAlg::EcdhEsA128Kw => circuit(input),
+        KeyAlg::Direct => {
+            (input.public.recipients.is_empty() && input.private.recipients.is_empty())
+                .xok_or(Error::RecipientCountMismatch)?;
+            circuit_content(
+                &input.public,
+                &input.private.transaction,
+                input.private.content_encryption_key,
+                false,
+            )
+        }
+    }
+}
+
+fn verify_input<'a>(input: &'a Input) -> Result<SafeTransaction<'a>, Error> {
+    // Reject structurally invalid inputs before doing any expensive
+    // cryptographic work over the transaction and ciphertext.
+    verify_structure(
+        &input.public,
+        input.private.content_encryption_key,
+        input.private.recipients.len(),
+    )?;
+    let transaction = verify_content(
+        &input.public,
         &input.private.transaction,
         input.private.content_encryption_key,
-        input.public.iv,
-    )
+        false,
+    )?;
+    verify_recipients(
+        &input.public,
+        input.private.content_encryption_key,
+        &input.private.recipients,
+    )?;
+    Ok(transaction)
+}
+
+/// Verifies the transaction and content encryption integrity.
+///
+/// This guarantees that the public ciphertext and tag are the encryption of a
+/// Safe transaction matching the struct hash and nonce under the content
+/// encryption key, and that the transaction does not delegatecall to the zero
+/// address. It makes no guarantees that any recipient can decrypt it.
+///
+/// With `aad_bind_nonce`, the tag must additionally authenticate the nonce, so
+/// the ciphertext cannot be replayed for another nonce even if struct hashes
+/// were to collide.
+pub fn circuit_content(
+    public: &PublicInput,
+    transaction: &[u8],
+    content_encryption_key: [u8; 16],
+    aad_bind_nonce: bool,
+) -> Result<(), Error> {
+    verify_content(public, transaction, content_encryption_key, aad_bind_nonce)?;
+    Ok(())
+}
+
+fn verify_content<'a>(
+    public: &PublicInput,
+    transaction: &'a [u8],
+    content_encryption_key: [u8; 16],
+    aad_bind_nonce: bool,
+) -> Result<SafeTransaction<'a>, Error> {
+    verify_content_encryption_key(content_encryption_key)?;
+    let decoded = verify_transaction(public, transaction)?;
+
+    // Verify the content encryption integrity.
+    let bound_nonce = aad_bind_nonce.then_some(public.nonce);
+    let (ciphertext, tag) = profile::content_encryption(|| {
+        encrypt::content(transaction, content_encryption_key, public.iv, bound_nonce)
+    })
     .map_err(|_| Error::ContentEncriptionFailure)?;
-    (*ciphertext == *input.public.ciphertext).xok_or(Error::CiphertextMismatch)?;
-    (tag == input.public.tag).xok_or(Error::TagMismatch)?;
+    (*ciphertext == *public.ciphertext).xok_or(Error::CiphertextMismatch)?;
+    (tag == public.tag).xok_or(Error::TagMismatch)?;
 
-    // Verify the key wrapping integrity.
-    (input.public.recipients.len() == input.private.recipients.len())
-        .xok_or(Error::RecipientCountMismatch)?;
-    for (public, private) in iter::zip(&*input.public.recipients, &*input.private.recipients) {
-        // Verify the ephemeral key integrity.
-        let ephemeral_public_key = ecdh::public_key(private.ephemeral_private_key);
-        (ephemeral_public_key == public.ephemeral_public_key)
-            .xok_or(Error::EphemeralKeyMismatch)?;
+    Ok(decoded)
+}
 
-        // Verify the content key encryption.
-        let shared_secret = ecdh::shared_secret(private.ephemeral_private_key, private.public_key);
-        let encrypted_key = encrypt::key(input.private.content_encryption_key, shared_secret)
-            .map_err(|_| Error::KeyEncriptionFailure)?;
-        (encrypted_key == public.encrypted_key).xok_or(Error::EncryptedKeyMismatch)?;
+fn verify_transaction<'a>(
+    public: &PublicInput,
+    transaction: &'a [u8],
+) -> Result<SafeTransaction<'a>, Error> {
+    profile::struct_hash(|| {
+        // Verify the transaction matches the struct hash.
+        let decoded =
+            SafeTransaction::decode(transaction).map_err(|_| Error::InvalidTransaction)?;
+        (decoded.operation != Operation::Delegatecall || decoded.to != [0; 20])
+            .xok_or(Error::ZeroDelegatecallTarget)?;
+        (decoded.struct_hash(public.nonce) == public.struct_hash)
+            .xok_or(Error::StructHashMismatch)?;
+        Ok(decoded)
+    })
+}
+
+/// Runs the same verification as [`circuit`], but accepts a tag computed
+/// under any of the candidate protected headers `aads`, returning the index of
+/// the first matching one.
+///
+/// This allows verifying ciphertexts produced under the additional
+/// authenticated data of an older format version without re-encrypting them.
+/// With `aad_bind_nonce`, each candidate additionally binds the nonce as in
+/// [`circuit_content`]. Pass `&[encrypt::AAD]` without binding the nonce to
+/// only accept the current header, which is equivalent to [`circuit`].
+///
+/// The tag is checked against each candidate by decrypting the ciphertext, so
+/// the content is only decrypted in full for the matching candidate. A
+/// ciphertext that no candidate authenticates, including one encrypted under
+/// another content encryption key, is reported as [`Error::TagMismatch`].
+pub fn circuit_aad(input: &Input, aads: &[&[u8]], aad_bind_nonce: bool) -> Result<usize, Error> {
+    let Input { public, private } = input;
+    verify_structure(
+        public,
+        private.content_encryption_key,
+        private.recipients.len(),
+    )?;
+    verify_transaction(public, &private.transaction)?;
+
+    let bound_nonce = aad_bind_nonce.then_some(public.nonce);
+    let (index, plaintext) = profile::content_encryption(|| {
+        aads.iter().enumerate().find_map(|(index, aad)| {
+            let plaintext = encrypt::decrypt_content_with_aad(
+                &public.ciphertext,
+                private.content_encryption_key,
+                public.iv,
+                public.tag,
+                &encrypt::bound_aad(aad, bound_nonce),
+            );
+            Some((index, plaintext.ok()?))
+        })
+    })
+    .ok_or(Error::TagMismatch)?;
+    (*plaintext == *private.transaction).xok_or(Error::CiphertextMismatch)?;
+
+    verify_recipients(public, private.content_encryption_key, &private.recipients)?;
+    Ok(index)
+}
+
+/// Verifies the key wrapping integrity.
+///
+/// This guarantees that each public recipient entry wraps the content
+/// encryption key for the corresponding recipient public key with a correctly
+/// derived ephemeral key, and that no ephemeral key is reused across
+/// recipients. On its own, it makes no guarantees about what the
+/// content encryption key decrypts.
+///
+/// Since every entry is checked against the same `content_encryption_key`,
+/// all recipients are guaranteed to unwrap the same key; an entry that unwraps
+/// to any other key is reported as [`Error::RecipientCekMismatch`], and one
+/// that does not unwrap for the recipient at all as
+/// [`Error::EncryptedKeyMismatch`].
+pub fn circuit_recipients(
+    public: &PublicInput,
+    content_encryption_key: [u8; 16],
+    recipients: &[PrivateRecipient],
+) -> Result<(), Error> {
+    verify_structure(public, content_encryption_key, recipients.len())?;
+    verify_recipients(public, content_encryption_key, recipients)
+}
+
+/// Verifies the key wrapping integrity of structurally verified recipients.
+fn verify_recipients(
+    public: &PublicInput,
+    content_encryption_key: [u8; 16],
+    recipients: &[PrivateRecipient],
+) -> Result<(), Error> {
+    for (index, (public, private)) in iter::zip(&*public.recipients, recipients).enumerate() {
+        verify_recipient_at(index, public, private, content_encryption_key)?;
     }
 
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Runs the same verification as [`circuit`], but only checks the key
+/// wrapping integrity of the recipients at `indices`.
+///
+/// This allows a large recipient set to be proven across multiple proofs,
+/// each covering a subset of the recipients. The struct hash and content
+/// checks are always performed. Note that the proof makes no guarantees about
+/// recipients outside of `indices`.
+pub fn circuit_recipients_subset(input: &Input, indices: &[usize]) -> Result<(), Error> {
+    let Input { public, private } = input;
+    verify_structure(
+        public,
+        private.content_encryption_key,
+        private.recipients.len(),
+    )?;
+    verify_content(
+        public,
+        &private.transaction,
+        private.content_encryption_key,
+        false,
+    )?;
+    for &index in indices {
+        let (public, private) = public
+            .recipients
+            .get(index)
+            .zip(private.recipients.get(index))
+            .ok_or(Error::RecipientIndexOutOfRange { index })?;
+        verify_recipient_at(index, public, private, input.private.content_encryption_key)?;
+    }
 
-    #[test]
-    fn test_circuit() {
-        let input = Input {
-            public: PublicInput {
-                struct_hash: *b"\xf2\x53\x54\xb3\x7b\xde\x8d\xfd\xfb\xeb\x63\x8a\x3e\x01\x0c\xdd\
-                                \x09\xff\x6a\x31\x9d\xbf\xb0\xab\x12\x58\x9d\xe2\x5d\x33\x52\xbe",
-                nonce: *b"\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\
-                          \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x05\x39",
-                ciphertext: Cow::Borrowed(
-                    b"\xbf\x39\x26\x1d\x44\x91\x66\x17\xd8\x53\xe3\x53\x8b\x2a\x09\x6f\
-                      \xfd\x7c\xe3\x23\x62\x10\xe6\x13\xed\x4d\xec\xca\x6e\x32\xe4\x69\
-                      \x6c\x4f\x8c\x24\x73\x4c\xce\x38\xa1\xce\x3a\x15\x00\xf7\x4f\x58\
+    Ok(())
+}
+
+fn verify_recipient_at(
+    index: usize,
+    public: &PublicRecipient,
+    private: &PrivateRecipient,
+    content_encryption_key: [u8; 16],
+) -> Result<(), Error> {
+    ecdh::validate_public_key(public.ephemeral_public_key)
+        .map_err(|_| Error::InvalidEphemeralKey { index })?;
+    verify_recipient(public, private, content_encryption_key)
+        .map_err(cek_mismatch_at(index, public, private))
+}
+
+/// Reports an encrypted key mismatch as a mismatch of the recipient at
+/// `index` if the entry unwraps to another content encryption key, leaving
+/// other errors, including entries not wrapped for the recipient, unchanged.
+fn cek_mismatch_at<'a>(
+    index: usize,
+    public: &'a PublicRecipient,
+    private: &'a PrivateRecipient,
+) -> impl FnOnce(Error) -> Error + 'a {
+    move |err| match err {
+        Error::EncryptedKeyMismatch => {
+            let shared_secret = Zeroizing::new(ecdh::shared_secret(
+                private.ephemeral_private_key,
+                private.public_key,
+            ));
+            match encrypt::unwrap_key(public.encrypted_key, *shared_secret) {
+                Ok(_) => Error::RecipientCekMismatch { index },
+                Err(_) => Error::EncryptedKeyMismatch,
+            }
+        }
+        err => err,
+    }
+}
+
+/// Verifies the key wrapping integrity from precomputed key encryption keys,
+/// for setups where the ECDH shared secret and derived key encryption key are
+/// computed in a secure enclave and never exposed.
+///
+/// This guarantees that each public recipient entry wraps the content
+/// encryption key under the corresponding key encryption key. Note that this
+/// is a weaker guarantee than [`circuit_recipients`]: it does **not** prove
+/// that the key encryption keys were derived from the public ephemeral keys,
+/// nor which recipient public keys they belong to.
+pub fn circuit_recipients_kek(
+    public: &PublicInput,
+    content_encryption_key: [u8; 16],
+    key_encryption_keys: &[[u8; 16]],
+) -> Result<(), Error> {
+    verify_structure(public, content_encryption_key, key_encryption_keys.len())?;
+    for (index, (public, &key_encryption_key)) in
+        iter::zip(&*public.recipients, key_encryption_keys).enumerate()
+    {
+        let encrypted_key = encrypt::wrap(content_encryption_key, key_encryption_key)
+            .map_err(|_| Error::KeyEncriptionFailure)?;
+        (encrypted_key == public.encrypted_key).xok_or(Error::RecipientCekMismatch { index })?;
+    }
+
+    Ok(())
+}
+
+/// Verifies that a recipient entry added to an existing public input wraps the
+/// same content encryption key as the existing entry at `existing_index`,
+/// without re-verifying the entire input.
+pub fn verify_added_recipient(
+    public: &PublicInput,
+    existing_index: usize,
+    private_existing: &PrivateRecipient,
+    new: &PublicRecipient,
+    private_new: &PrivateRecipient,
+    content_encryption_key: [u8; 16],
+) -> Result<(), Error> {
+    verify_content_encryption_key(content_encryption_key)?;
+    let existing =
+        public
+            .recipients
+            .get(existing_index)
+            .ok_or(Error::RecipientIndexOutOfRange {
+                index: existing_index,
+            })?;
+    for (a, recipient) in public.recipients.iter().enumerate() {
+        (recipient.ephemeral_public_key != new.ephemeral_public_key).xok_or(
+            Error::EphemeralKeyReuse {
+                a,
+                b: public.recipients.len(),
+            },
+        )?;
+    }
+
+    verify_recipient_at(
+        existing_index,
+        existing,
+        private_existing,
+        content_encryption_key,
+    )?;
+    verify_recipient_at(
+        public.recipients.len(),
+        new,
+        private_new,
+        content_encryption_key,
+    )
+}
+
+fn verify_structure(
+    public: &PublicInput,
+    content_encryption_key: [u8; 16],
+    recipient_count: usize,
+) -> Result<(), Error> {
+    verify_content_encryption_key(content_encryption_key)?;
+    // A zero struct hash is not a plausible Keccak-256 output. Note that a
+    // zero nonce is valid, as it is the first nonce of every Safe.
+    (public.struct_hash != [0; 32]).xok_or(Error::ZeroStructHash)?;
+    (public.recipients.len() == recipient_count).xok_or(Error::RecipientCountMismatch)?;
+    verify_distinct_ephemeral_keys(&public.recipients)
+}
+
+fn verify_content_encryption_key(content_encryption_key: [u8; 16]) -> Result<(), Error> {
+    // This cannot catch all weak keys, but catches the most common failure of
+    // an uninitialized key buffer.
+    (content_encryption_key != [0; 16]).xok_or(Error::WeakKey)
+}
+
+fn verify_distinct_ephemeral_keys(recipients: &[PublicRecipient]) -> Result<(), Error> {
+    for (a, recipient) in recipients.iter().enumerate() {
+        for (b, other) in recipients.iter().enumerate().skip(a + 1) {
+            (recipient.ephemeral_public_key != other.ephemeral_public_key)
+                .xok_or(Error::EphemeralKeyReuse { a, b })?;
+        }
+    }
+    Ok(())
+}
+
+fn verify_recipient(
+    public: &PublicRecipient,
+    private: &PrivateRecipient,
+    content_encryption_key: [u8; 16],
+) -> Result<(), Error> {
+    profile::key_wrap(|| {
+        // Verify the ephemeral key integrity.
+        let ephemeral_public_key = ecdh::public_key(private.ephemeral_private_key);
+        (ephemeral_public_key == public.ephemeral_public_key)
+            .xok_or(Error::EphemeralKeyMismatch)?;
+
+        // Verify the content key encryption.
+        let shared_secret = ecdh::shared_secret(private.ephemeral_private_key, private.public_key);
+        let encrypted_key = encrypt::key(content_encryption_key, shared_secret)
+            .map_err(|_| Error::KeyEncriptionFailure)?;
+        (encrypted_key == public.encrypted_key).xok_or(Error::EncryptedKeyMismatch)
+    })
+}
+
+/// Verifies the circuit input and that the Safe transaction is one of an
+/// allowlisted set of struct hashes, without revealing which one.
+pub fn circuit_allowlist(input: &Input, allowed_struct_hashes: &[[u8; 32]]) -> Result<(), Error> {
+    circuit(input)?;
+    allowed_struct_hashes
+        .contains(&input.public.struct_hash)
+        .xok_or(Error::NotAllowlisted)
+}
+
+/// Verifies the circuit input and that the Safe transaction operation is
+/// allowed by the `allowed_operations` bitmask, where bit 0 allows calls and
+/// bit 1 allows delegatecalls.
+pub fn circuit_operations(input: &Input, allowed_operations: u8) -> Result<(), Error> {
+    let op = verify_input(input)?.operation;
+    (allowed_operations & (1 << op as u8) != 0).xok_or(Error::OperationNotAllowed { op })
+}
+
+/// Verifies the circuit input and that the Safe transaction transfers at most
+/// `max_value` wei, both as big-endian 256-bit unsigned integers.
+pub fn circuit_value_limit(input: &Input, max_value: [u8; 32]) -> Result<(), Error> {
+    let transaction = verify_input(input)?;
+    (u256::cmp(&transaction.value, &max_value) != Ordering::Greater)
+        .xok_or(Error::ValueExceedsLimit)
+}
+
+/// Verifies the circuit input and that the Safe transaction's `safeTxGas` and
+/// `gasPrice` are at most `max_safe_tx_gas` and `max_gas_price` respectively,
+/// bounding the refund a relayer may be asked to pay.
+pub fn circuit_gas_cap(
+    input: &Input,
+    max_safe_tx_gas: [u8; 32],
+    max_gas_price: [u8; 32],
+) -> Result<(), Error> {
+    let transaction = verify_input(input)?;
+    (u256::cmp(&transaction.safe_tx_gas, &max_safe_tx_gas) != Ordering::Greater
+        && u256::cmp(&transaction.gas_price, &max_gas_price) != Ordering::Greater)
+        .xok_or(Error::GasExceedsLimit)
+}
+
+/// Verifies the circuit input and that the public nonce is within the
+/// inclusive range from `min` to `max`, both as big-endian 256-bit unsigned
+/// integers.
+pub fn circuit_nonce_range(input: &Input, min: [u8; 32], max: [u8; 32]) -> Result<(), Error> {
+    verify_input(input)?;
+    let nonce = &input.public.nonce;
+    (u256::cmp(&min, nonce) != Ordering::Greater && u256::cmp(nonce, &max) != Ordering::Greater)
+        .xok_or(Error::NonceOutOfRange)
+}
+
+/// Verifies a circuit input whose public input commits to the recipients,
+/// checking that the recipient entries match the commitment.
+///
+/// This binds the set of recipients the content encryption key is wrapped for
+/// with a single public value, so the public input does not grow with the
+/// number of recipients and the verifier does not need to know every
+/// recipient public key.
+pub fn circuit_recipients_commitment(input: &CommittedInput) -> Result<(), Error> {
+    (recipients_commitment(&input.recipients, &input.private.recipients)
+        == input.public.recipients_commitment)
+        .xok_or(Error::RecipientsCommitmentMismatch)?;
+    circuit(&input.expand())
+}
+
+/// Verifies the circuit input and that its struct hash and ciphertext match
+/// `payload_commitment`, as computed by [`PublicInput::payload_commitment`].
+///
+/// This prevents pairing a valid ciphertext of one Safe transaction with the
+/// struct hash of another when the two are published separately, as only the
+/// pairing that was committed to is accepted.
+pub fn circuit_payload_commitment(
+    input: &Input,
+    payload_commitment: [u8; 32],
+) -> Result<(), Error> {
+    (input.public.payload_commitment() == payload_commitment)
+        .xok_or(Error::PayloadCommitmentMismatch)?;
+    circuit(input)
+}
+
+/// Computes the commitment to a list of recipients, the Keccak-256 hash of
+/// the concatenated public key, ephemeral public key and encrypted key of each
+/// recipient in order.
+///
+/// Only as many recipients as the shorter of the two lists are committed to,
+/// the circuit rejects inputs where their lengths differ.
+pub fn recipients_commitment(public: &[PublicRecipient], private: &[PrivateRecipient]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for (public, private) in iter::zip(public, private) {
+        hasher.update(private.public_key);
+        hasher.update(public.ephemeral_public_key);
+        hasher.update(public.encrypted_key);
+    }
+    hasher.finalize().into()
+}
+
+/// Verifies the circuit input and that the Safe transaction produces exactly
+/// the EIP-712 Safe transaction hash `expected_digest` for the Safe with
+/// `domain_separator`.
+///
+/// This binds the proof to the hash that owners sign on-chain, for verifiers
+/// that receive it from an oracle or indexer instead of the struct hash.
+pub fn circuit_digest(
+    input: &Input,
+    domain_separator: [u8; 32],
+    expected_digest: [u8; 32],
+) -> Result<(), Error> {
+    let transaction = verify_input(input)?;
+    verify_against_contract_hash(
+        &transaction,
+        input.public.nonce,
+        domain_separator,
+        expected_digest,
+    )
+}
+
+/// Verifies that a Safe transaction hashes to the Safe transaction hash
+/// computed on-chain by the Safe contract's `getTransactionHash`.
+///
+/// On mismatch, the error includes the computed struct hash and Safe
+/// transaction hash in order to help diagnose encoding issues.
+pub fn verify_against_contract_hash(
+    transaction: &SafeTransaction,
+    nonce: [u8; 32],
+    domain_separator: [u8; 32],
+    expected_safe_tx_hash: [u8; 32],
+) -> Result<(), Error> {
+    let safe_tx_hash = transaction.eip712_digest(nonce, domain_separator);
+    (safe_tx_hash == expected_safe_tx_hash).xok_or_else(|| Error::SafeTxHashMismatch {
+        struct_hash: transaction.struct_hash(nonce),
+        safe_tx_hash,
+    })
+}
+
+/// Recovers the content encryption key wrapped for a recipient with the
+/// recipient's private key.
+///
+/// The returned key, and the shared secret it is unwrapped with, are wiped
+/// when dropped.
+pub fn unwrap_key(
+    recipient: &PublicRecipient,
+    private_key: [u8; 32],
+) -> Result<Zeroizing<[u8; 16]>, Error> {
+    let shared_secret = Zeroizing::new(ecdh::shared_secret(
+        private_key,
+        recipient.ephemeral_public_key,
+    ));
+    encrypt::unwrap_key(recipient.encrypted_key, *shared_secret)
+        .map_err(|_| Error::KeyDecryptionFailure)
+}
+
+/// Recovers the content encryption key from every recipient with the
+/// corresponding private key, checking that all recipients wrap the same key.
+///
+/// This is intended for owners holding all recipient private keys, for
+/// example for escrow or recovery, to confirm that no recipient was given a
+/// different key. The returned key is wiped when dropped.
+pub fn recover_all(
+    public: &PublicInput,
+    private_keys: &[[u8; 32]],
+) -> Result<Zeroizing<[u8; 16]>, Error> {
+    (public.recipients.len() == private_keys.len()).xok_or(Error::RecipientCountMismatch)?;
+    let mut content_encryption_key = None;
+    for (index, (recipient, &private_key)) in
+        iter::zip(&*public.recipients, private_keys).enumerate()
+    {
+        let key = unwrap_key(recipient, private_key)?;
+        match &content_encryption_key {
+            Some(first) => (*first == key).xok_or(Error::RecipientCekMismatch { index })?,
+            None => content_encryption_key = Some(key),
+        }
+    }
+    content_encryption_key.ok_or(Error::RecipientCountMismatch)
+}
+
+/// Decrypts the public ciphertext with a directly supplied content encryption
+/// key, skipping the key agreement and unwrapping entirely.
+///
+/// This is useful for [`KeyAlg::Direct`] and for testing. The ciphertext must
+/// have been encrypted without nonce binding, and a wrong key is reported as
+/// [`Error::TagMismatch`].
+pub fn recover_plaintext_with_cek(
+    public: &PublicInput,
+    content_encryption_key: [u8; 16],
+) -> Result<Vec<u8>, Error> {
+    encrypt::decrypt_content(
+        &public.ciphertext,
+        content_encryption_key,
+        public.iv,
+        public.tag,
+    )
+    .map_err(|_| Error::TagMismatch)
+}
+
+/// Sorts recipients by their ephemeral public keys, keeping the public and
+/// private recipient lists aligned.
+///
+/// Recipient order is committed to by the public input, so if used, the
+/// producer and verifier must agree on the canonical ordering.
+pub fn canonicalize_recipients(
+    public: &mut [PublicRecipient],
+    private: &mut [PrivateRecipient],
+) -> Result<(), Error> {
+    (public.len() == private.len()).xok_or(Error::RecipientCountMismatch)?;
+    let mut pairs = iter::zip(public.iter().cloned(), private.iter().cloned()).collect::<Vec<_>>();
+    pairs.sort_by_key(|(public, _)| public.ephemeral_public_key);
+    for ((public, private), (sorted_public, sorted_private)) in
+        iter::zip(iter::zip(public, private), pairs)
+    {
+        *public = sorted_public;
+        *private = sorted_private;
+    }
+    Ok(())
+}
+
+/// Re-wraps the content encryption key for a new set of recipients, keeping
+/// the ciphertext and struct hash commitment unchanged. Each recipient gets a
+/// fresh ephemeral private key from `rng`.
+///
+/// The content encryption key must decrypt the ciphertext, with or without the
+/// nonce bound, so that the new recipients are never given a key that does
+/// not decrypt it.
+pub fn rewrap(
+    public: &PublicInput,
+    content_encryption_key: [u8; 16],
+    new_recipients: &[[u8; 32]],
+    mut rng: impl FnMut() -> [u8; 32],
+) -> Result<PublicInput<'static>, Error> {
+    verify_content_encryption_key(content_encryption_key)?;
+    let decrypts = |bound_nonce| {
+        encrypt::decrypt_content_with_aad(
+            &public.ciphertext,
+            content_encryption_key,
+            public.iv,
+            public.tag,
+            &encrypt::bound_aad(encrypt::default_aad(), bound_nonce),
+        )
+        .map(Zeroizing::new)
+        .is_ok()
+    };
+    (decrypts(None) || decrypts(Some(public.nonce))).xok_or(Error::TagMismatch)?;
+
+    let recipients = new_recipients
+        .iter()
+        .enumerate()
+        .map(|(index, &public_key)| {
+            ecdh::validate_public_key(public_key).map_err(|_| Error::InvalidPublicKey { index })?;
+            let ephemeral_private_key = Zeroizing::new(rng());
+            let shared_secret =
+                Zeroizing::new(ecdh::shared_secret(*ephemeral_private_key, public_key));
+            Ok(PublicRecipient {
+                encrypted_key: encrypt::key(content_encryption_key, *shared_secret)
+                    .map_err(|_| Error::KeyEncriptionFailure)?,
+                ephemeral_public_key: ecdh::public_key(*ephemeral_private_key),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(PublicInput {
+        struct_hash: public.struct_hash,
+        nonce: public.nonce,
+        ciphertext: public.ciphertext.to_vec().into(),
+        iv: public.iv,
+        tag: public.tag,
+        recipients: recipients.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{panic, thread};
+
+    /// The sampled RLP encoded public input.
+    pub(crate) const PUBLIC: &str = "0xf90145a0f25354b37bde8dfdfbeb638a3e010cdd09ff6a319dbfb0ab12589de2\
+                                       5d3352be820539b84bbf39261d44916617d853e3538b2a096ffd7ce3236210e6\
+                                       13ed4decca6e32e4696c4f8c24734cce38a1ce3a1500f74f58b575188b33d4e8\
+                                       ed8961aa9f0f6407db788e7f1fd5af28db6001fb8cb05c984165f2d23a28000d\
+                                       4b9008e67b91dcd38c7a1f48b93b59ffe1b8f8b4f83a98590a3a98e58dadf522\
+                                       baa91357ec1d0f4f5305c6dd885745a0fb74a081098bcfe6e6c1840bea1194b9\
+                                       2c7e41912fc2347cbe0cbc7fa4a4857af83a986de31be4920402f1348ebd4431\
+                                       6a35ca7a0af9657d863b03a01083b3b5529465bb436d52ccf5c887da31a687ad\
+                                       778ffe0c0bc58b0d81811333f83a983f04b1dd42337e71b0421be845c9bc1e2a\
+                                       7fcf9c45c62681a072cda02de475ad6f654f66796160377c65a26684a4f1d4b2\
+                                       9dcb225ca180bd29";
+
+    /// The sampled RLP encoded private input.
+    pub(crate) const PRIVATE: &str = "0xf9012cb84bf84994a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a102840304\
+                                        05060107080994a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a294a3a3a3a3\
+                                        a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a390c3ba3d49dd84aaf39f49478324bc31\
+                                        69f8ccf842a032487b2e70917797e376aed50c85902eea2c42ba4fad257a6c6b\
+                                        b93e47e80b2fa068dd94fb8d7ca504c59fdcfd1413d7202eecbbb252ab3bbcdb\
+                                        6e4697b4d3e463f842a0029bfe0f900e8ac0e6a98aa3ffde0ad93b46f52a5a37\
+                                        43b9ce88296ca2385168a02065df9b0385a913255081ca19e9153391e41e3ff8\
+                                        f3c2426c2878114cd2be66f842a0201ef1b77e2b56130b358749711812f6fcc6\
+                                        d1543c425c32f5f5c0408731f20aa0b01923b73b27127f61932b21501a516475\
+                                        922f0aa50f5b56cff2eeafa0521c4b";
+
+    /// The sampled combined RLP encoded input.
+    pub(crate) fn combined() -> Vec<u8> {
+        let public = hex::decode(PUBLIC).unwrap();
+        let private = hex::decode(PRIVATE).unwrap();
+        let len = (public.len() + private.len()) as u16;
+        [&[0xf9][..], &len.to_be_bytes(), &public, &private].concat()
+    }
+
+    /// Decodes the sampled circuit input from its RLP encoding.
+    pub(crate) fn decoded() -> Input<'static> {
+        Input {
+            public: PublicInput::decode(&hex::decode(PUBLIC).unwrap()).unwrap(),
+            private: PrivateInput::decode(&hex::decode(PRIVATE).unwrap()).unwrap(),
+        }
+    }
+
+    /// Returns the sampled circuit input.
+    pub(crate) fn input() -> Input<'static> {
+        Input {
+            public: PublicInput {
+                struct_hash: *b"\xf2\x53\x54\xb3\x7b\xde\x8d\xfd\xfb\xeb\x63\x8a\x3e\x01\x0c\xdd\
+                                \x09\xff\x6a\x31\x9d\xbf\xb0\xab\x12\x58\x9d\xe2\x5d\x33\x52\xbe",
+                nonce: *b"\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\
+                          \x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x05\x39",
+                ciphertext: Cow::Borrowed(
+                    b"\xbf\x39\x26\x1d\x44\x91\x66\x17\xd8\x53\xe3\x53\x8b\x2a\x09\x6f\
+                      \xfd\x7c\xe3\x23\x62\x10\xe6\x13\xed\x4d\xec\xca\x6e\x32\xe4\x69\
+                      \x6c\x4f\x8c\x24\x73\x4c\xce\x38\xa1\xce\x3a\x15\x00\xf7\x4f\x58\
                       \xb5\x75\x18\x8b\x33\xd4\xe8\xed\x89\x61\xaa\x9f\x0f\x64\x07\xdb\
                       \x78\x8e\x7f\x1f\xd5\xaf\x28\xdb\x60\x01\xfb",
                 ),
                 iv: *b"\xb0\x5c\x98\x41\x65\xf2\xd2\x3a\x28\x00\x0d\x4b",
                 tag: *b"\x08\xe6\x7b\x91\xdc\xd3\x8c\x7a\x1f\x48\xb9\x3b\x59\xff\xe1\xb8",
-                recipients: Cow::Borrowed(&[
+                recipients: Cow::Owned(vec![
                     PublicRecipient {
                         encrypted_key:
                             *b"\x59\x0a\x3a\x98\xe5\x8d\xad\xf5\x22\xba\xa9\x13\x57\xec\x1d\x0f\
@@ -181,7 +1746,7 @@ mod tests {
                 ),
                 content_encryption_key:
                     *b"\xc3\xba\x3d\x49\xdd\x84\xaa\xf3\x9f\x49\x47\x83\x24\xbc\x31\x69",
-                recipients: Cow::Borrowed(&[
+                recipients: Cow::Owned(vec![
                     PrivateRecipient {
                         public_key:
                             *b"\x32\x48\x7b\x2e\x70\x91\x77\x97\xe3\x76\xae\xd5\x0c\x85\x90\x2e\
@@ -208,7 +1773,1385 @@ mod tests {
                     },
                 ]),
             },
+        }
+    }
+
+    /// Re-encrypts an input after its private transaction was modified.
+    pub(crate) fn reencrypt(input: &mut Input) {
+        let transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+        input.public.struct_hash = transaction.struct_hash(input.public.nonce);
+        let (ciphertext, tag) = encrypt::content(
+            &input.private.transaction,
+            input.private.content_encryption_key,
+            input.public.iv,
+            None,
+        )
+        .unwrap();
+        input.public.ciphertext = ciphertext.into();
+        input.public.tag = tag;
+    }
+
+    /// Asserts that the circuit fails on `input` with exactly `expected`,
+    /// reporting a panic in the circuit as a test failure instead of an
+    /// unrelated panic message.
+    ///
+    /// This does not format the errors, as `Error` only implements `Debug`
+    /// with debug assertions.
+    #[track_caller]
+    pub(crate) fn assert_circuit_err(input: &Input, expected: Error) {
+        match panic::catch_unwind(|| circuit(input)) {
+            Ok(Err(err)) => assert!(err == expected, "circuit failed with another error"),
+            Ok(Ok(())) => panic!("circuit succeeded, expected an error"),
+            Err(_) => panic!("circuit panicked, expected an error"),
+        }
+    }
+
+    #[test]
+    fn test_circuit() {
+        assert!(circuit(&input()).is_ok());
+    }
+
+    #[test]
+    fn test_circuit_tampered() {
+        let tampered = |f: fn(&mut Input)| {
+            let mut input = input();
+            f(&mut input);
+            input
+        };
+
+        assert_circuit_err(
+            &tampered(|input| input.public.struct_hash[0] ^= 1),
+            Error::StructHashMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.public.nonce[31] ^= 1),
+            Error::StructHashMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.public.ciphertext.to_mut()[0] ^= 1),
+            Error::CiphertextMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.public.iv[0] ^= 1),
+            Error::CiphertextMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.public.tag[0] ^= 1),
+            Error::TagMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.public.recipients.to_mut()[1].encrypted_key[0] ^= 1),
+            Error::EncryptedKeyMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| {
+                input.public.recipients.to_mut()[1].ephemeral_public_key =
+                    ecdh::public_key([0x55; 32]);
+            }),
+            Error::EphemeralKeyMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.public.recipients.to_mut().truncate(2)),
+            Error::RecipientCountMismatch,
+        );
+
+        assert_circuit_err(
+            &tampered(|input| input.private.transaction.to_mut()[0] ^= 1),
+            Error::InvalidTransaction,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.private.transaction.to_mut()[23] ^= 1),
+            Error::StructHashMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.private.content_encryption_key[0] ^= 1),
+            Error::CiphertextMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| input.private.recipients.to_mut()[2].public_key[0] ^= 1),
+            Error::EncryptedKeyMismatch,
+        );
+        assert_circuit_err(
+            &tampered(|input| {
+                input.private.recipients.to_mut()[0].ephemeral_private_key[0] ^= 0x40
+            }),
+            Error::EphemeralKeyMismatch,
+        );
+    }
+
+    #[test]
+    fn test_circuit_decode() {
+        let Ok(transaction) = circuit_decode(&input()) else {
+            panic!("circuit failed");
+        };
+        assert_eq!(transaction.to, [0xa1; 20]);
+        assert_eq!(transaction.value[31], 2);
+        assert_eq!(*transaction.data, [3, 4, 5, 6]);
+        assert!(transaction.operation == Operation::Delegatecall);
+        assert_eq!(transaction.safe_tx_gas[31], 7);
+        assert_eq!(transaction.gas_gas[31], 8);
+        assert_eq!(transaction.gas_price[31], 9);
+        assert_eq!(transaction.gas_token, [0xa2; 20]);
+        assert_eq!(transaction.refund_reciver, [0xa3; 20]);
+
+        let mut input = input();
+        input.public.tag[0] ^= 1;
+        assert!(matches!(circuit_decode(&input), Err(Error::TagMismatch)));
+    }
+
+    #[test]
+    fn test_circuit_fast_reject() {
+        // Corrupt the ciphertext so that the content verification would fail
+        // if it ran before the recipient count check.
+        let mut input = input();
+        input.public.ciphertext.to_mut()[0] ^= 1;
+        input.private.recipients.to_mut().pop();
+        assert_circuit_err(&input, Error::RecipientCountMismatch);
+
+        // The ciphertext is never recomputed once the recipient count check
+        // fails, as content encryption panics with fault injection enabled.
+        encrypt::tests::FAIL_CONTENT.set(true);
+        assert_circuit_err(&input, Error::RecipientCountMismatch);
+        assert!(matches!(
+            panic::catch_unwind(|| circuit_aad(&input, &[encrypt::AAD], false)),
+            Ok(Err(Error::RecipientCountMismatch)),
+        ));
+        encrypt::tests::FAIL_CONTENT.set(false);
+        assert!(
+            panic::catch_unwind(|| {
+                encrypt::tests::FAIL_CONTENT.set(true);
+                circuit(&self::input())
+            })
+            .is_err()
+        );
+        encrypt::tests::FAIL_CONTENT.set(false);
+    }
+
+    #[test]
+    fn test_circuit_key_alg() {
+        let mut input = input();
+        assert!(circuit_key_alg(&input, KeyAlg::EcdhEsA128Kw).is_ok());
+        assert!(matches!(
+            circuit_key_alg(&input, KeyAlg::Direct),
+            Err(Error::RecipientCountMismatch),
+        ));
+
+        input.public.recipients = Cow::Borrowed(&[]);
+        input.private.recipients = Cow::Borrowed(&[]);
+        assert!(circuit_key_alg(&input, KeyAlg::Direct).is_ok());
+
+        input.private.content_encryption_key[0] ^= 1;
+        assert!(matches!(
+            circuit_key_alg(&input, KeyAlg::Direct),
+            Err(Error::CiphertextMismatch),
+        ));
+    }
+
+    #[test]
+    fn test_circuit_canonical() {
+        let mut input = input();
+        assert!(circuit_canonical(&input).is_ok());
+
+        // Re-encode the transaction with a leading zero byte in the value.
+        let transaction = SafeTransaction::decode(&input.private.transaction)
+            .unwrap()
+            .into_owned();
+        let mut items = rlp::Decoder::new(&input.private.transaction)
+            .list()
+            .unwrap();
+        let mut encoder = rlp::Encoder::new();
+        encoder.list(|fields| {
+            let mut index = 0;
+            while let Some(item) = items.next().unwrap() {
+                let bytes = item.as_bytes().unwrap();
+                match index {
+                    1 => fields.bytes(&[&[0], bytes].concat()),
+                    _ => fields.bytes(bytes),
+                }
+                index += 1;
+            }
+        });
+        input.private.transaction = encoder.finish().into();
+        reencrypt(&mut input);
+
+        assert!(SafeTransaction::decode(&input.private.transaction).unwrap() == transaction);
+        assert!(circuit(&input).is_ok());
+        assert!(circuit_canonical(&input) == Err(Error::NonCanonicalTransaction));
+    }
+
+    #[test]
+    fn test_circuit_recipients_subset() {
+        let mut input = input();
+        assert!(circuit_recipients_subset(&input, &[1]).is_ok());
+        assert!(circuit_recipients_subset(&input, &[0, 2]).is_ok());
+        assert!(
+            circuit_recipients_subset(&input, &[0, 3])
+                == Err(Error::RecipientIndexOutOfRange { index: 3 })
+        );
+
+        input.public.recipients.to_mut()[1].encrypted_key[0] ^= 1;
+        assert!(circuit_recipients_subset(&input, &[0, 2]).is_ok());
+        assert!(circuit_recipients_subset(&input, &[1]) == Err(Error::EncryptedKeyMismatch));
+    }
+
+    #[test]
+    fn test_circuit_aad() {
+        let input = input();
+        let legacy = br#"eyJlbmMiOiJBMTI4R0NNIiwidiI6MH0"#.as_slice();
+        let other = b"other".as_slice();
+
+        assert!(matches!(circuit_aad(&input, &[encrypt::AAD], false), Ok(0)));
+        assert!(matches!(
+            circuit_aad(&input, &[encrypt::AAD, legacy], false),
+            Ok(0)
+        ));
+        assert!(matches!(
+            circuit_aad(&input, &[legacy, encrypt::AAD], false),
+            Ok(1)
+        ));
+        assert!(matches!(
+            circuit_aad(&input, &[legacy, other], false),
+            Err(Error::TagMismatch),
+        ));
+        assert!(matches!(
+            circuit_aad(&input, &[], false),
+            Err(Error::TagMismatch)
+        ));
+
+        let mut input = input;
+        let (_, tag) = encrypt::content_with_aad(
+            &input.private.transaction,
+            input.private.content_encryption_key,
+            input.public.iv,
+            legacy,
+        )
+        .unwrap();
+        input.public.tag = tag;
+        assert_circuit_err(&input, Error::TagMismatch);
+        assert!(matches!(
+            circuit_aad(&input, &[encrypt::AAD, legacy], false),
+            Ok(1)
+        ));
+        assert!(matches!(
+            circuit_aad(&input, &[encrypt::AAD, legacy], true),
+            Err(Error::TagMismatch),
+        ));
+
+        let (_, tag) = encrypt::content_with_aad(
+            &input.private.transaction,
+            input.private.content_encryption_key,
+            input.public.iv,
+            &encrypt::bound_aad(legacy, Some(input.public.nonce)),
+        )
+        .unwrap();
+        input.public.tag = tag;
+        assert!(matches!(
+            circuit_aad(&input, &[encrypt::AAD, legacy], true),
+            Ok(1)
+        ));
+        assert!(matches!(
+            circuit_aad(&input, &[encrypt::AAD, legacy], false),
+            Err(Error::TagMismatch),
+        ));
+    }
+
+    #[test]
+    fn test_circuit_partial() {
+        let Input { public, private } = input();
+        let cek = private.content_encryption_key;
+
+        assert!(circuit_content(&public, &private.transaction, cek, false).is_ok());
+        assert!(circuit_recipients(&public, cek, &private.recipients).is_ok());
+
+        assert!(matches!(
+            circuit_content(&public, &private.transaction, [1; 16], false),
+            Err(Error::CiphertextMismatch),
+        ));
+        assert!(matches!(
+            circuit_content(&public, &private.transaction, cek, true),
+            Err(Error::TagMismatch),
+        ));
+        assert!(matches!(
+            circuit_recipients(&public, cek, &private.recipients[1..]),
+            Err(Error::RecipientCountMismatch),
+        ));
+    }
+
+    #[test]
+    fn test_circuit_recipients_kek() {
+        let Input { public, private } = input();
+        let cek = private.content_encryption_key;
+        let mut keks = private
+            .recipients
+            .iter()
+            .map(|recipient| {
+                encrypt::kdf(ecdh::shared_secret(
+                    recipient.ephemeral_private_key,
+                    recipient.public_key,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        assert!(circuit_recipients(&public, cek, &private.recipients).is_ok());
+        assert!(circuit_recipients_kek(&public, cek, &keks).is_ok());
+
+        keks[1][0] ^= 1;
+        assert!(matches!(
+            circuit_recipients_kek(&public, cek, &keks),
+            Err(Error::RecipientCekMismatch { index: 1 }),
+        ));
+        assert!(matches!(
+            circuit_recipients_kek(&public, cek, &keks[..2]),
+            Err(Error::RecipientCountMismatch),
+        ));
+    }
+
+    #[test]
+    fn test_recipient_cek_mismatch() {
+        let mut input = input();
+        let recipient = &input.private.recipients[1];
+        input.public.recipients.to_mut()[1].encrypted_key = encrypt::key(
+            [0x33; 16],
+            ecdh::shared_secret(recipient.ephemeral_private_key, recipient.public_key),
+        )
+        .unwrap();
+        assert_circuit_err(&input, Error::RecipientCekMismatch { index: 1 });
+    }
+
+    #[test]
+    fn test_invalid_ephemeral_key() {
+        let mut input = input();
+        input.public.recipients.to_mut()[2].ephemeral_public_key = [0; 32];
+        assert_circuit_err(&input, Error::InvalidEphemeralKey { index: 2 });
+    }
+
+    #[test]
+    fn test_weak_key() {
+        let mut input = input();
+        input.private.content_encryption_key = [0; 16];
+        reencrypt(&mut input);
+        assert_circuit_err(&input, Error::WeakKey);
+    }
+
+    #[test]
+    fn test_zero_struct_hash() {
+        let mut input = input();
+        assert!(circuit(&input).is_ok());
+
+        // Corrupt the ciphertext so that the check must run before the
+        // content verification.
+        input.public.struct_hash = [0; 32];
+        input.public.ciphertext.to_mut()[0] ^= 1;
+        assert_circuit_err(&input, Error::ZeroStructHash);
+    }
+
+    #[test]
+    fn test_ephemeral_key_reuse() {
+        let mut input = input();
+        let recipients = input.public.recipients.to_mut();
+        recipients[2].ephemeral_public_key = recipients[0].ephemeral_public_key;
+        assert_circuit_err(&input, Error::EphemeralKeyReuse { a: 0, b: 2 });
+    }
+
+    #[test]
+    fn test_zero_delegatecall_target() {
+        let mut input = input();
+        input.private.transaction.to_mut()[3..23].fill(0);
+        reencrypt(&mut input);
+        assert_circuit_err(&input, Error::ZeroDelegatecallTarget);
+
+        // The same transaction as a call is allowed.
+        input.private.transaction.to_mut()[29] = 0x80;
+        reencrypt(&mut input);
+        assert!(circuit(&input).is_ok());
+    }
+
+    #[test]
+    fn test_circuit_operations() {
+        let delegatecall = input();
+        let mut call = input();
+        call.private.transaction.to_mut()[29] = 0x80;
+        reencrypt(&mut call);
+
+        for allowed_operations in 0..4 {
+            for (input, op) in [
+                (&call, Operation::Call),
+                (&delegatecall, Operation::Delegatecall),
+            ] {
+                let result = circuit_operations(input, allowed_operations);
+                if allowed_operations & (1 << op as u8) != 0 {
+                    assert!(result.is_ok());
+                } else {
+                    assert!(matches!(
+                        result,
+                        Err(Error::OperationNotAllowed { op: got }) if got == op,
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_circuit_value_limit() {
+        let input = input();
+        assert!(circuit_value_limit(&input, u256::from_u64(2)).is_ok());
+        assert!(circuit_value_limit(&input, u256::from_u64(3)).is_ok());
+        assert!(circuit_value_limit(&input, [0xff; 32]).is_ok());
+        assert!(matches!(
+            circuit_value_limit(&input, u256::from_u64(1)),
+            Err(Error::ValueExceedsLimit),
+        ));
+
+        // Values that only differ in their most significant bytes.
+        let mut high = [0; 32];
+        high[0] = 1;
+        let mut large = input.clone();
+        let mut transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+        transaction.value = high;
+        large.private.transaction = transaction.encode().into();
+        reencrypt(&mut large);
+        assert!(circuit_value_limit(&large, high).is_ok());
+        assert!(circuit_value_limit(&large, [0xff; 32]).is_ok());
+        let mut below = [0xff; 32];
+        below[0] = 0;
+        assert!(matches!(
+            circuit_value_limit(&large, below),
+            Err(Error::ValueExceedsLimit),
+        ));
+        assert!(matches!(
+            circuit_value_limit(&large, u256::from_u64(u64::MAX)),
+            Err(Error::ValueExceedsLimit),
+        ));
+    }
+
+    #[test]
+    fn test_circuit_gas_cap() {
+        let input = input();
+        for (max_safe_tx_gas, max_gas_price) in [(7, 9), (8, 9), (7, 10), (u64::MAX, u64::MAX)] {
+            assert!(
+                circuit_gas_cap(
+                    &input,
+                    u256::from_u64(max_safe_tx_gas),
+                    u256::from_u64(max_gas_price),
+                )
+                .is_ok()
+            );
+        }
+        for (max_safe_tx_gas, max_gas_price) in [(6, 9), (7, 8), (0, 0)] {
+            assert!(matches!(
+                circuit_gas_cap(
+                    &input,
+                    u256::from_u64(max_safe_tx_gas),
+                    u256::from_u64(max_gas_price),
+                ),
+                Err(Error::GasExceedsLimit),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_circuit_recipients_commitment() {
+        let input = input();
+        let mut hasher = Keccak256::new();
+        for (public, private) in iter::zip(&*input.public.recipients, &*input.private.recipients) {
+            hasher.update(private.public_key);
+            hasher.update(public.ephemeral_public_key);
+            hasher.update(public.encrypted_key);
+        }
+        let commitment: [u8; 32] = hasher.finalize().into();
+        assert_eq!(
+            recipients_commitment(&input.public.recipients, &input.private.recipients),
+            commitment,
+        );
+
+        let committed = input.commit_recipients();
+        assert_eq!(committed.public.recipients_commitment, commitment);
+        assert!(committed.expand() == input);
+        assert!(circuit_recipients_commitment(&committed).is_ok());
+
+        let mut reordered = committed.clone();
+        reordered.private.recipients.to_mut().swap(0, 1);
+        assert!(matches!(
+            circuit_recipients_commitment(&reordered),
+            Err(Error::RecipientsCommitmentMismatch),
+        ));
+        let mut rewrapped = committed.clone();
+        rewrapped.recipients.to_mut()[1].encrypted_key[0] ^= 1;
+        assert!(matches!(
+            circuit_recipients_commitment(&rewrapped),
+            Err(Error::RecipientsCommitmentMismatch),
+        ));
+        let mut ephemeral = committed.clone();
+        ephemeral.recipients.to_mut()[1].ephemeral_public_key[0] ^= 1;
+        assert!(matches!(
+            circuit_recipients_commitment(&ephemeral),
+            Err(Error::RecipientsCommitmentMismatch),
+        ));
+        let mut zero = committed.clone();
+        zero.public.recipients_commitment = [0; 32];
+        assert!(matches!(
+            circuit_recipients_commitment(&zero),
+            Err(Error::RecipientsCommitmentMismatch),
+        ));
+
+        // The commitment does not replace the key wrapping checks.
+        let mut swapped = input.clone();
+        swapped.private.recipients.to_mut()[0].public_key = [9; 32];
+        assert!(matches!(
+            circuit_recipients_commitment(&swapped.commit_recipients()),
+            Err(Error::EncryptedKeyMismatch),
+        ));
+    }
+
+    #[test]
+    fn test_circuit_payload_commitment() {
+        let input = input();
+        let commitment = input.public.payload_commitment();
+        let mut hasher = Keccak256::new();
+        hasher.update(input.public.struct_hash);
+        hasher.update(input.public.ciphertext_hash());
+        assert_eq!(commitment, <[u8; 32]>::from(hasher.finalize()));
+        assert!(circuit_payload_commitment(&input, commitment).is_ok());
+
+        // A ciphertext of the same transaction under a different IV is valid
+        // on its own, but was not committed to.
+        let mut other = input.clone();
+        other.public.iv[0] ^= 1;
+        reencrypt(&mut other);
+        assert!(circuit(&other).is_ok());
+        assert!(matches!(
+            circuit_payload_commitment(&other, commitment),
+            Err(Error::PayloadCommitmentMismatch),
+        ));
+
+        // A different transaction with its own valid ciphertext.
+        let mut other = input.clone();
+        other.public.nonce = u256::from_u64(1338);
+        reencrypt(&mut other);
+        assert!(circuit(&other).is_ok());
+        assert!(matches!(
+            circuit_payload_commitment(&other, commitment),
+            Err(Error::PayloadCommitmentMismatch),
+        ));
+        assert!(circuit_payload_commitment(&other, other.public.payload_commitment()).is_ok());
+    }
+
+    #[test]
+    fn test_circuit_nonce_range() {
+        let input = input();
+        let nonce = u256::from_u64(1337);
+        assert_eq!(input.public.nonce, nonce);
+
+        for (min, max) in [(1337, 1337), (1337, 2000), (0, 1337), (1000, 2000)] {
+            assert!(circuit_nonce_range(&input, u256::from_u64(min), u256::from_u64(max)).is_ok());
+        }
+        assert!(circuit_nonce_range(&input, [0; 32], [0xff; 32]).is_ok());
+        for (min, max) in [(1338, 2000), (0, 1336), (2000, 1000)] {
+            assert!(matches!(
+                circuit_nonce_range(&input, u256::from_u64(min), u256::from_u64(max)),
+                Err(Error::NonceOutOfRange),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_verify_added_recipient() {
+        let Input { public, private } = input();
+        let cek = private.content_encryption_key;
+        let private_new = PrivateRecipient {
+            public_key: ecdh::public_key([0x11; 32]),
+            ephemeral_private_key: [0x22; 32],
+        };
+        let new = PublicRecipient {
+            encrypted_key: encrypt::key(
+                cek,
+                ecdh::shared_secret(private_new.ephemeral_private_key, private_new.public_key),
+            )
+            .unwrap(),
+            ephemeral_public_key: ecdh::public_key(private_new.ephemeral_private_key),
+        };
+
+        assert!(
+            verify_added_recipient(&public, 1, &private.recipients[1], &new, &private_new, cek)
+                .is_ok()
+        );
+        assert!(matches!(
+            verify_added_recipient(&public, 0, &private.recipients[1], &new, &private_new, cek),
+            Err(Error::EphemeralKeyMismatch),
+        ));
+        assert!(matches!(
+            verify_added_recipient(&public, 3, &private.recipients[1], &new, &private_new, cek),
+            Err(Error::RecipientIndexOutOfRange { index: 3 }),
+        ));
+
+        let small_order = PublicRecipient {
+            ephemeral_public_key: [0; 32],
+            ..new
+        };
+        assert!(matches!(
+            verify_added_recipient(
+                &public,
+                1,
+                &private.recipients[1],
+                &small_order,
+                &private_new,
+                cek
+            ),
+            Err(Error::InvalidEphemeralKey { index: 3 }),
+        ));
+
+        let other = PublicRecipient {
+            encrypted_key: encrypt::key(
+                [0x33; 16],
+                ecdh::shared_secret(private_new.ephemeral_private_key, private_new.public_key),
+            )
+            .unwrap(),
+            ..new
+        };
+        assert!(matches!(
+            verify_added_recipient(
+                &public,
+                1,
+                &private.recipients[1],
+                &other,
+                &private_new,
+                cek
+            ),
+            Err(Error::RecipientCekMismatch { index: 3 }),
+        ));
+        assert!(matches!(
+            verify_added_recipient(
+                &public,
+                1,
+                &private.recipients[1],
+                &new,
+                &private_new,
+                [0x55; 16]
+            ),
+            Err(Error::RecipientCekMismatch { index: 1 }),
+        ));
+        assert!(matches!(
+            verify_added_recipient(
+                &public,
+                1,
+                &private.recipients[1],
+                &new,
+                &private_new,
+                [0; 16]
+            ),
+            Err(Error::WeakKey),
+        ));
+    }
+
+    #[test]
+    fn test_verify_against_contract_hash() {
+        let input = input();
+        let transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+        let domain_separator = safe::domain_separator(
+            u256::from_u64(1),
+            *b"\x5a\xfe\x38\x55\x35\x8e\x11\x2b\x56\x47\xb9\x52\x70\x9e\x61\x65\xe1\xc1\xee\xee",
+        );
+        assert_eq!(
+            domain_separator,
+            *b"\xc0\x3d\x57\x20\x6a\xeb\x95\xbb\x28\x16\xe4\x4c\xec\x2a\xea\xff\
+               \x23\xab\x39\xc4\x10\xe5\x48\xf0\x1c\xe7\xfe\x7b\x6a\xa6\x97\xec",
+        );
+        let safe_tx_hash = *b"\x35\x55\x11\xc2\x70\x9f\x8d\x3c\xa6\xa4\xbd\x16\x77\x56\x6d\x25\
+                              \x38\xe6\x6c\x9e\x87\x4a\xa4\xd1\x54\x20\xff\x32\xf7\x90\xd5\xb9";
+        assert!(
+            verify_against_contract_hash(
+                &transaction,
+                input.public.nonce,
+                domain_separator,
+                safe_tx_hash,
+            )
+            .is_ok()
+        );
+        match verify_against_contract_hash(
+            &transaction,
+            u256::from_u64(1338),
+            domain_separator,
+            safe_tx_hash,
+        ) {
+            Err(Error::SafeTxHashMismatch { struct_hash, .. }) => {
+                assert_eq!(struct_hash, transaction.struct_hash(u256::from_u64(1338)));
+            }
+            _ => panic!("expected a Safe transaction hash mismatch"),
+        }
+
+        let transfer = SafeTransaction {
+            to:
+                *b"\xd9\xdb\x27\x0c\x1b\x5e\x3b\xd1\x61\xe8\xc8\x50\x3c\x55\xce\xab\xee\x70\x95\x52",
+            value: u256::from_u64(1_000_000_000_000_000_000),
+            data: Cow::Borrowed(&[]),
+            operation: Operation::Call,
+            safe_tx_gas: [0; 32],
+            gas_gas: [0; 32],
+            gas_price: [0; 32],
+            gas_token: [0; 20],
+            refund_reciver: [0; 20],
         };
+        let domain_separator = safe::domain_separator(
+            u256::from_u64(100),
+            *b"\x8c\xf6\x0b\x28\x9f\x8d\x31\xf7\x37\x04\x9b\x59\x0b\x5e\x42\x85\xff\x0b\xd1\xd1",
+        );
+        assert_eq!(
+            transfer.struct_hash(u256::from_u64(5)),
+            *b"\x2c\xc2\xf2\xa3\x47\x62\xb7\xb3\xc4\x24\x1a\x18\xbf\x0a\x36\x9b\
+               \xbf\xd8\x37\xb8\x47\x36\x6b\x0c\xc3\xb4\xe8\x13\x24\xbf\x2d\x26",
+        );
+        assert!(
+            verify_against_contract_hash(
+                &transfer,
+                u256::from_u64(5),
+                domain_separator,
+                *b"\x39\x8b\x4f\x7e\xa8\x55\x03\xcd\x07\xbe\x5d\x20\x28\xc4\xcd\x30\
+                   \xd9\xa9\x6d\x4b\x25\x24\x31\x22\xc3\x84\x3e\xcb\xbc\x05\x39\xbc",
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_circuit_digest() {
+        let mut input = input();
+        let domain_separator = *b"\xc0\x3d\x57\x20\x6a\xeb\x95\xbb\x28\x16\xe4\x4c\xec\x2a\xea\xff\
+               \x23\xab\x39\xc4\x10\xe5\x48\xf0\x1c\xe7\xfe\x7b\x6a\xa6\x97\xec";
+        let safe_tx_hash = *b"\x35\x55\x11\xc2\x70\x9f\x8d\x3c\xa6\xa4\xbd\x16\x77\x56\x6d\x25\
+                              \x38\xe6\x6c\x9e\x87\x4a\xa4\xd1\x54\x20\xff\x32\xf7\x90\xd5\xb9";
+        assert!(circuit_digest(&input, domain_separator, safe_tx_hash).is_ok());
+
+        // The same transaction on another chain.
+        let other_chain = safe::domain_separator(
+            u256::from_u64(100),
+            *b"\x5a\xfe\x38\x55\x35\x8e\x11\x2b\x56\x47\xb9\x52\x70\x9e\x61\x65\xe1\xc1\xee\xee",
+        );
+        assert!(matches!(
+            circuit_digest(&input, other_chain, safe_tx_hash),
+            Err(Error::SafeTxHashMismatch { struct_hash, .. }) if struct_hash == input.public.struct_hash,
+        ));
+
+        // A valid input for another nonce.
+        input.public.nonce = u256::from_u64(1338);
+        reencrypt(&mut input);
         assert!(circuit(&input).is_ok());
+        assert!(matches!(
+            circuit_digest(&input, domain_separator, safe_tx_hash),
+            Err(Error::SafeTxHashMismatch { .. }),
+        ));
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let expected = input().public;
+        let public = PublicInput::from_parts(
+            &expected.struct_hash,
+            &expected.nonce,
+            &*expected.ciphertext,
+            &expected.iv,
+            &expected.tag,
+            &*expected.recipients,
+        );
+        assert!(matches!(public, Ok(public) if public == expected));
+
+        assert!(matches!(
+            PublicInput::from_parts(
+                &expected.struct_hash,
+                &expected.nonce,
+                &*expected.ciphertext,
+                &expected.iv[1..],
+                &expected.tag,
+                &*expected.recipients,
+            ),
+            Err(FromPartsError::InvalidLength {
+                field: "iv",
+                expected: 12,
+                got: 11,
+            }),
+        ));
+        assert!(matches!(
+            PublicInput::from_parts(
+                &expected.struct_hash,
+                &expected.nonce,
+                &*expected.ciphertext,
+                &expected.iv,
+                &expected.tag,
+                &[][..],
+            ),
+            Err(FromPartsError::NoRecipients),
+        ));
+    }
+
+    #[test]
+    fn test_self_consistent() {
+        let mut input = input();
+        assert!(
+            input
+                .public
+                .self_consistent(&input.private.transaction)
+                .is_ok()
+        );
+        assert!(matches!(
+            input.public.self_consistent(b"\xc0"),
+            Err(Error::InvalidTransaction)
+        ));
+
+        input.public.nonce[31] ^= 1;
+        assert!(matches!(
+            input.public.self_consistent(&input.private.transaction),
+            Err(Error::StructHashMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_recipient_count() {
+        let mut public = input().public;
+        assert_eq!(public.recipient_count(), 3);
+        public.recipients.to_mut().pop();
+        assert_eq!(public.recipient_count(), 2);
+    }
+
+    #[test]
+    fn test_ciphertext_hash() {
+        let mut input = input();
+        let hash = input.public.ciphertext_hash();
+        assert_eq!(input.clone().public.ciphertext_hash(), hash);
+
+        input.public.tag[0] ^= 1;
+        assert_ne!(input.public.ciphertext_hash(), hash);
+    }
+
+    #[test]
+    fn test_encode() {
+        let input = input();
+        assert_eq!(input.encode(), combined());
+        assert_eq!(input.public.encode(), hex::decode(PUBLIC).unwrap());
+        assert_eq!(input.private.encode(), hex::decode(PRIVATE).unwrap());
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        let mut input = input();
+        assert_eq!(input.public.encoded_len(), input.public.encode().len());
+        assert_eq!(input.private.encoded_len(), input.private.encode().len());
+
+        input.public.nonce = [0; 32];
+        input.public.recipients = Cow::Borrowed(&[]);
+        input.private.transaction = Cow::Owned(vec![0; 0x100]);
+        assert_eq!(input.public.encoded_len(), input.public.encode().len());
+        assert_eq!(input.private.encoded_len(), input.private.encode().len());
+    }
+
+    #[test]
+    fn test_eq() {
+        let input = input();
+        assert!(decoded() == input);
+        let round_trip = Input::decode(&input.encode()).unwrap();
+        assert!(Input::decode(&round_trip.encode()).unwrap() == round_trip);
+        assert!(round_trip == input);
+
+        let mut tampered = input.clone();
+        tampered.public.recipients.to_mut()[2].encrypted_key[0] ^= 1;
+        assert!(tampered != input);
+        let mut tampered = input.clone();
+        tampered.private.transaction.to_mut()[0] ^= 1;
+        assert!(tampered != input);
+    }
+
+    #[test]
+    fn test_to_wasi_args() {
+        let [public, private] = input().to_wasi_args();
+        assert!(public.starts_with("0x") && private.starts_with("0x"));
+
+        let input = Input {
+            public: PublicInput::decode(&hex::decode(&public).unwrap()).unwrap(),
+            private: PrivateInput::decode(&hex::decode(&private).unwrap()).unwrap(),
+        };
+        assert!(circuit(&input).is_ok());
+    }
+
+    #[test]
+    fn test_try_from_bytes() {
+        let combined = combined();
+        let input = Input::try_from(combined.as_slice()).unwrap();
+        assert!(matches!(input.public.ciphertext, Cow::Borrowed(_)));
+        assert!(matches!(input.private.transaction, Cow::Borrowed(_)));
+        assert!(circuit(&input).is_ok());
+
+        assert!(Input::try_from(&combined[..combined.len() - 1]).is_err());
+        assert!(Input::try_from(b"\xc2\xc0\xc0".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_decode_double_encoded() {
+        let mut encoder = rlp::Encoder::new();
+        encoder.bytes(hex::encode(&combined()).as_bytes());
+        let encoded = encoder.finish();
+        assert!(matches!(
+            Input::decode(&encoded),
+            Err(rlp::Error::ExpectedList),
+        ));
+        assert!(matches!(
+            PublicInput::decode(&encoded),
+            Err(rlp::Error::ExpectedList),
+        ));
+        assert!(matches!(Input::decode(&[]), Err(rlp::Error::UnexpectedEof),));
+    }
+
+    #[test]
+    fn test_decode_borrowed() {
+        let public = hex::decode(PUBLIC).unwrap();
+        let private = hex::decode(PRIVATE).unwrap();
+        let input = Input::decode_borrowed(&public, &private).unwrap();
+        assert!(circuit(&input).is_ok());
+        assert!(input == self::input());
+
+        let Cow::Borrowed(ciphertext) = input.public.ciphertext else {
+            panic!("ciphertext was copied");
+        };
+        assert!(public.as_ptr_range().contains(&ciphertext.as_ptr()));
+        let Cow::Borrowed(transaction) = input.private.transaction else {
+            panic!("transaction was copied");
+        };
+        assert!(private.as_ptr_range().contains(&transaction.as_ptr()));
+
+        assert!(Input::decode_borrowed(&private, &public).is_err());
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Input<'static>>();
+        assert_send_sync::<PublicInput<'static>>();
+        assert_send_sync::<PrivateInput<'static>>();
+        assert_send_sync::<Error>();
+
+        let inputs = [input(), input()];
+        thread::scope(|s| {
+            let workers = inputs
+                .iter()
+                .map(|input| s.spawn(|| circuit(input).is_ok()))
+                .collect::<Vec<_>>();
+            for worker in workers {
+                assert!(worker.join().unwrap());
+            }
+        });
+    }
+
+    #[test]
+    fn test_clone() {
+        let input = decoded();
+        let clone = input.clone();
+        drop(input);
+        assert!(circuit(&clone).is_ok());
+
+        let buf = combined();
+        let static_input = Input::try_from(&*buf).unwrap().to_static();
+        drop(buf);
+        assert!(circuit(&static_input).is_ok());
+    }
+
+    #[test]
+    fn test_recipient_fingerprint() {
+        let input = input();
+        let [a, b, ..] = &*input.public.recipients else {
+            panic!("sampled input has fewer than two recipients");
+        };
+        assert_eq!(a.fingerprint(), a.clone().fingerprint());
+        assert_ne!(a.fingerprint(), b.fingerprint());
+
+        let mut hasher = Keccak256::new();
+        hasher.update(a.ephemeral_public_key);
+        hasher.update(a.encrypted_key);
+        assert_eq!(a.fingerprint(), hasher.finalize()[..8]);
+    }
+
+    #[test]
+    fn test_recipient_bytes() {
+        let Input { public, private } = input();
+        let mut hasher = Keccak256::new();
+        for (public, private) in iter::zip(&*public.recipients, &*private.recipients) {
+            hasher.update(public.encrypted_key_bytes());
+            hasher.update(public.ephemeral_public_key_bytes());
+            hasher.update(private.public_key_bytes());
+        }
+
+        let mut expected = Keccak256::new();
+        for (public, private) in iter::zip(&*public.recipients, &*private.recipients) {
+            expected.update(public.encrypted_key);
+            expected.update(public.ephemeral_public_key);
+            expected.update(private.public_key);
+        }
+        assert_eq!(hasher.finalize(), expected.finalize());
+    }
+
+    #[test]
+    fn test_canonicalize_recipients() {
+        let Input { public, private } = input();
+        let (mut public_recipients, mut private_recipients) =
+            (public.recipients.to_vec(), private.recipients.to_vec());
+        assert!(canonicalize_recipients(&mut public_recipients, &mut private_recipients).is_ok());
+        assert!(
+            public_recipients
+                .windows(2)
+                .all(|w| w[0].ephemeral_public_key <= w[1].ephemeral_public_key)
+        );
+
+        let canonical = PublicInput {
+            recipients: public_recipients.clone().into(),
+            ..public.clone()
+        };
+        assert!(
+            circuit_recipients(
+                &canonical,
+                private.content_encryption_key,
+                &private_recipients
+            )
+            .is_ok()
+        );
+
+        let (mut public_again, mut private_again) =
+            (public_recipients.clone(), private_recipients.clone());
+        assert!(canonicalize_recipients(&mut public_again, &mut private_again).is_ok());
+        assert!(public_again == public_recipients);
+        assert!(private_again == private_recipients);
+
+        assert!(matches!(
+            canonicalize_recipients(&mut public_again, &mut private_again[1..]),
+            Err(Error::RecipientCountMismatch),
+        ));
+    }
+
+    #[test]
+    fn test_rewrap() {
+        let input = input();
+        let private_keys = [[0x11; 32], [0x22; 32]];
+        let public_keys = private_keys.map(ecdh::public_key);
+
+        let mut ephemeral_private_keys = [[0x33; 32], [0x44; 32]].into_iter();
+        let Ok(public) = rewrap(
+            &input.public,
+            input.private.content_encryption_key,
+            &public_keys,
+            || ephemeral_private_keys.next().unwrap(),
+        ) else {
+            panic!("rewrap failed");
+        };
+
+        assert_eq!(public.struct_hash, input.public.struct_hash);
+        assert_eq!(public.ciphertext, input.public.ciphertext);
+        assert_eq!(public.iv, input.public.iv);
+        assert_eq!(public.tag, input.public.tag);
+        assert_eq!(public.recipients.len(), 2);
+        for (recipient, private_key) in iter::zip(&*public.recipients, private_keys) {
+            assert!(matches!(
+                unwrap_key(recipient, private_key),
+                Ok(key) if *key == input.private.content_encryption_key,
+            ));
+        }
+
+        let rng = || [0x33; 32];
+        assert!(matches!(
+            rewrap(&input.public, [0x55; 16], &public_keys, rng),
+            Err(Error::TagMismatch),
+        ));
+        assert!(matches!(
+            rewrap(
+                &input.public,
+                input.private.content_encryption_key,
+                &[public_keys[0], [0; 32]],
+                rng,
+            ),
+            Err(Error::InvalidPublicKey { index: 1 }),
+        ));
+
+        let (ciphertext, tag) = encrypt::content(
+            &input.private.transaction,
+            input.private.content_encryption_key,
+            input.public.iv,
+            Some(input.public.nonce),
+        )
+        .unwrap();
+        let bound = PublicInput {
+            ciphertext: ciphertext.into(),
+            tag,
+            ..input.public.clone()
+        };
+        assert!(
+            rewrap(
+                &bound,
+                input.private.content_encryption_key,
+                &public_keys,
+                rng
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_recover_all() {
+        let input = input();
+        let private_keys = [[0x11; 32], [0x22; 32]];
+        let public_keys = private_keys.map(ecdh::public_key);
+        let rng = || [0x33; 32];
+
+        let Ok(public) = rewrap(
+            &input.public,
+            input.private.content_encryption_key,
+            &public_keys,
+            rng,
+        ) else {
+            panic!("rewrap failed");
+        };
+        assert!(
+            recover_all(&public, &private_keys)
+                == Ok(Zeroizing::new(input.private.content_encryption_key))
+        );
+        assert!(recover_all(&public, &private_keys[..1]) == Err(Error::RecipientCountMismatch));
+
+        let mut recipients = public.recipients.to_vec();
+        recipients[1].encrypted_key =
+            encrypt::key([0x55; 16], ecdh::shared_secret(rng(), public_keys[1])).unwrap();
+        let public = PublicInput {
+            recipients: recipients.into(),
+            ..public
+        };
+        assert!(
+            recover_all(&public, &private_keys) == Err(Error::RecipientCekMismatch { index: 1 })
+        );
+    }
+
+    #[test]
+    fn test_recover_plaintext_with_cek() {
+        let input = input();
+        let key = input.private.content_encryption_key;
+        assert!(matches!(
+            recover_plaintext_with_cek(&input.public, key),
+            Ok(plaintext) if *plaintext == *input.private.transaction,
+        ));
+
+        let (ciphertext, tag) = encrypt::content(b"hello", key, input.public.iv, None).unwrap();
+        let public = PublicInput {
+            ciphertext: ciphertext.into(),
+            tag,
+            ..input.public
+        };
+        assert!(matches!(
+            recover_plaintext_with_cek(&public, key),
+            Ok(plaintext) if *plaintext == *b"hello",
+        ));
+        assert!(matches!(
+            recover_plaintext_with_cek(&public, [0x42; 16]),
+            Err(Error::TagMismatch),
+        ));
+    }
+
+    #[test]
+    fn test_zeroizing_keys() {
+        let input = input();
+        let private_key = [0x11; 32];
+        let Ok(public) = rewrap(
+            &input.public,
+            input.private.content_encryption_key,
+            &[ecdh::public_key(private_key)],
+            || [0x33; 32],
+        ) else {
+            panic!("rewrap failed");
+        };
+
+        let Ok::<Zeroizing<[u8; 16]>, _>(key) = unwrap_key(&public.recipients[0], private_key)
+        else {
+            panic!("unwrapping the key failed");
+        };
+        let plaintext =
+            encrypt::decrypt_content(&public.ciphertext, *key, public.iv, public.tag).unwrap();
+        assert_eq!(plaintext, *input.private.transaction);
+
+        let Ok::<Zeroizing<[u8; 16]>, _>(key) = recover_all(&public, &[private_key]) else {
+            panic!("recovering the key failed");
+        };
+        assert!(encrypt::decrypt_content(&public.ciphertext, *key, public.iv, public.tag).is_ok());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let combined = hex::encode(&combined());
+        assert!(combined.parse::<Input>().unwrap() == input());
+
+        let Err(err) = "0xzz".parse::<Input>() else {
+            panic!("parsed invalid hex");
+        };
+        assert!(matches!(err, ParseInputError::Hex(_)));
+        assert_eq!(
+            err.to_string(),
+            "invalid combined input: invalid 0x-prefixed hexadecimal string",
+        );
+
+        let Err(err) = PUBLIC.parse::<Input>() else {
+            panic!("parsed a public input as a combined input");
+        };
+        assert!(matches!(err, ParseInputError::Rlp(_)));
+        assert_eq!(
+            err.to_string(),
+            "invalid combined input: invalid RLP encoding"
+        );
+        assert!(error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn test_decode_recipients_into() {
+        let public = hex::decode(PUBLIC).unwrap();
+        let recipients = || {
+            let mut list = rlp::Decoder::new(&public).list().unwrap();
+            for _ in 0..5 {
+                list.next().unwrap();
+            }
+            list
+        };
+
+        let empty = PublicRecipient {
+            encrypted_key: [0; 24],
+            ephemeral_public_key: [0; 32],
+        };
+        let mut out: [_; 4] = std::array::from_fn(|_| empty.clone());
+        let count = PublicRecipient::decode_vec_into(&mut recipients(), &mut out).unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(out[0].encrypted_key[..4], [0x59, 0x0a, 0x3a, 0x98]);
+        assert_eq!(out[2].encrypted_key[..4], [0x3f, 0x04, 0xb1, 0xdd]);
+        assert_eq!(out[3].encrypted_key, [0; 24]);
+
+        let mut out = [empty.clone(), empty];
+        assert!(matches!(
+            PublicRecipient::decode_vec_into(&mut recipients(), &mut out),
+            Err(rlp::Error::TooManyItems { capacity: 2 }),
+        ));
+    }
+
+    #[test]
+    fn test_curve_tagged_keys() {
+        let input = input();
+        let encoded = input.encode_with(KeyEncoding::CurveTagged);
+        let mut tagged_key = vec![0xa1, Curve::X25519.id()];
+        tagged_key.extend_from_slice(&input.public.recipients[0].ephemeral_public_key);
+        assert!(
+            encoded
+                .windows(tagged_key.len())
+                .any(|window| window == tagged_key)
+        );
+        assert_eq!(input.encode_with(KeyEncoding::Raw), input.encode());
+
+        let decoded = Input::decode_with(&encoded, KeyEncoding::CurveTagged).unwrap();
+        assert!(decoded == input);
+        assert!(circuit(&decoded).is_ok());
+        assert!(Input::decode(&encoded).is_err());
+        assert!(Input::decode_with(&combined(), KeyEncoding::CurveTagged).is_err());
+
+        let mut encoded = encoded;
+        let offset = encoded
+            .windows(tagged_key.len())
+            .position(|window| window == tagged_key)
+            .unwrap();
+        encoded[offset + 1] = 1;
+        assert!(matches!(
+            Input::decode_with(&encoded, KeyEncoding::CurveTagged),
+            Err(DecodeError::UnknownCurve { id: 1 }),
+        ));
+    }
+
+    #[test]
+    fn test_envelope() {
+        let input = input();
+        let envelope = input.encode_envelope();
+        assert_eq!(envelope[0], ENVELOPE_VERSION);
+        assert_eq!(envelope[1..], combined());
+        assert!(matches!(Input::decode_envelope(&envelope), Ok(decoded) if decoded == input));
+
+        let mut future = envelope.clone();
+        future[0] = ENVELOPE_VERSION + 1;
+        assert!(matches!(
+            Input::decode_envelope(&future),
+            Err(EnvelopeError::UnsupportedVersion { version }) if version == ENVELOPE_VERSION + 1,
+        ));
+
+        assert!(matches!(
+            Input::decode_envelope(&[]),
+            Err(EnvelopeError::Truncated),
+        ));
+        assert!(matches!(
+            Input::decode_envelope(&envelope[..envelope.len() - 1]),
+            Err(EnvelopeError::Rlp(_)),
+        ));
+    }
+
+    #[test]
+    fn test_read_framed() {
+        let combined = combined();
+        let mut framed = (combined.len() as u32).to_be_bytes().to_vec();
+        framed.extend_from_slice(&combined);
+
+        let input = Input::read_framed(&mut io::Cursor::new(&framed)).unwrap();
+        assert!(circuit(&input).is_ok());
+
+        let err = Input::read_framed(&mut io::Cursor::new(&framed[..framed.len() - 1]))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let err = Input::read_framed(&mut io::Cursor::new([0xff; 4]))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_read_rlp() {
+        let combined = combined();
+        let stream = [&combined[..], b"\x42"].concat();
+        let mut reader = io::BufReader::with_capacity(1, stream.as_slice());
+        let input = Input::read_rlp(&mut reader).unwrap();
+        assert!(circuit(&input).is_ok());
+        assert!(matches!(reader.fill_buf().unwrap(), b"\x42"));
+
+        let err = Input::read_rlp(&mut &b""[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let err = Input::read_rlp(&mut &b"\xc0"[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let owned = {
+            let Input { public, private } = input();
+            let ciphertext = public.ciphertext.to_vec();
+            let transaction = private.transaction.to_vec();
+            let borrowed = Input {
+                public: PublicInput {
+                    ciphertext: Cow::Borrowed(&ciphertext),
+                    recipients: Cow::Borrowed(&public.recipients),
+                    ..public.clone()
+                },
+                private: PrivateInput {
+                    transaction: Cow::Borrowed(&transaction),
+                    recipients: Cow::Borrowed(&private.recipients),
+                    ..private.clone()
+                },
+            };
+            borrowed.into_owned()
+        };
+        assert!(matches!(owned.public.ciphertext, Cow::Owned(_)));
+        assert!(matches!(owned.private.transaction, Cow::Owned(_)));
+        assert!(owned == input());
+        assert!(circuit(&owned).is_ok());
+    }
+
+    #[test]
+    fn test_circuit_allowlist() {
+        let input = input();
+        let allowed = [[0xaa; 32], input.public.struct_hash];
+        assert!(circuit_allowlist(&input, &allowed).is_ok());
+        assert!(matches!(
+            circuit_allowlist(&input, &allowed[..1]),
+            Err(Error::NotAllowlisted),
+        ));
     }
 }
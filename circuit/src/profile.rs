@@ -0,0 +1,55 @@
+//! Execution region markers for proof system cycle accounting.
+//!
+//! With the `profiling` feature, each region is a distinct non-inlined
+//! function, so that zkVM backend tooling can attribute cycles to the major
+//! phases of the circuit by symbol. Otherwise, regions are inlined away.
+
+/// Marks the Safe transaction decoding and struct hash region.
+#[cfg_attr(feature = "profiling", inline(never))]
+#[cfg_attr(not(feature = "profiling"), inline(always))]
+pub fn struct_hash<T>(f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+/// Marks the content encryption region.
+#[cfg_attr(feature = "profiling", inline(never))]
+#[cfg_attr(not(feature = "profiling"), inline(always))]
+pub fn content_encryption<T>(f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+/// Marks the per-recipient ECDH and key wrapping region.
+#[cfg_attr(feature = "profiling", inline(never))]
+#[cfg_attr(not(feature = "profiling"), inline(always))]
+pub fn key_wrap<T>(f: impl FnOnce() -> T) -> T {
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{circuit, encrypt, safe::SafeTransaction, tests::input};
+
+    #[test]
+    fn test_regions_are_transparent() {
+        let input = input();
+        let transaction =
+            struct_hash(|| SafeTransaction::decode(&input.private.transaction)).unwrap();
+        assert_eq!(
+            struct_hash(|| transaction.struct_hash(input.public.nonce)),
+            input.public.struct_hash,
+        );
+        let (ciphertext, tag) = content_encryption(|| {
+            encrypt::content(
+                &input.private.transaction,
+                input.private.content_encryption_key,
+                input.public.iv,
+                None,
+            )
+        })
+        .unwrap();
+        assert_eq!(ciphertext, *input.public.ciphertext);
+        assert_eq!(tag, input.public.tag);
+        assert!(key_wrap(|| circuit(&input)).is_ok());
+    }
+}
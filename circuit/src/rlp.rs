@@ -1,4 +1,9 @@
-//! Poor-man's RLP decoder.
+//! Poor-man's RLP decoder and encoder.
+
+use std::{
+    array, error, fmt,
+    io::{self, BufRead, Read as _},
+};
 
 /// An RLP decoder.
 pub struct Decoder<'a>(&'a [u8]);
@@ -11,6 +16,34 @@ pub enum Item<'a> {
     List(Decoder<'a>),
 }
 
+impl<'a> Item<'a> {
+    /// Returns the byte string of a bytes item.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Self::Bytes(data) => Some(data),
+            Self::List(_) => None,
+        }
+    }
+
+    /// Returns the decoder over the items of a list item.
+    pub fn as_list(self) -> Option<Decoder<'a>> {
+        match self {
+            Self::Bytes(_) => None,
+            Self::List(list) => Some(list),
+        }
+    }
+
+    /// Returns whether the item is a byte string.
+    pub fn is_bytes(&self) -> bool {
+        matches!(self, Self::Bytes(_))
+    }
+
+    /// Returns whether the item is a list.
+    pub fn is_list(&self) -> bool {
+        matches!(self, Self::List(_))
+    }
+}
+
 impl<'a> Decoder<'a> {
     /// Create a new RLP decoder.
     pub fn new(data: &'a [u8]) -> Self {
@@ -18,42 +51,62 @@ impl<'a> Decoder<'a> {
     }
 
     /// Decodes a struct from an RLP-encoded list.
-    pub fn decode_struct<T, F>(&mut self, f: F) -> Result<T, Error>
+    ///
+    /// Errors with [`Error::ExpectedList`] if the item is a byte string, which
+    /// is usually a sign of input that was encoded twice. If `f` reads past the
+    /// last item of the list, this errors with [`Error::UnexpectedEof`], and if
+    /// `f` leaves items unread, with [`Error::TrailingData`].
+    pub fn decode_struct<T, E, F>(&mut self, f: F) -> Result<T, E>
     where
         T: 'a,
-        F: FnOnce(&mut Decoder<'a>) -> Result<T, Error> + 'a,
+        E: From<Error>,
+        F: FnOnce(&mut Decoder<'a>) -> Result<T, E> + 'a,
     {
-        let mut list = self.list()?;
+        let mut list = self.item()?.as_list().ok_or(Error::ExpectedList)?;
         self.done()?;
         let result = f(&mut list)?;
+        let remaining = list.count()?;
+        (remaining == 0)
+            .then_some(result)
+            .ok_or_else(|| Error::TrailingData { remaining }.into())
+    }
+
+    /// Decodes a list of exactly `N` items.
+    pub fn decode_fixed<const N: usize>(&mut self) -> Result<[Item<'a>; N], Error> {
+        let mut list = self.list()?;
+        let mut error = None;
+        let items = array::from_fn(|_| {
+            // Stop reading at the first error, which is returned instead of
+            // the partially filled array.
+            if error.is_none() {
+                match list.item() {
+                    Ok(item) => return item,
+                    Err(err) => error = Some(err),
+                }
+            }
+            Item::Bytes(&[])
+        });
+        if let Some(err) = error {
+            return Err(err);
+        }
         list.done()?;
-        Ok(result)
+        Ok(items)
     }
 
     /// Decodes a list item.
     pub fn list(&mut self) -> Result<Self, Error> {
-        match self.next()? {
-            Some(Item::List(list)) => Ok(list),
-            _ => Err(Error),
-        }
+        self.item()?.as_list().ok_or(Error::Invalid)
     }
 
     /// Decodes a vector.
-    pub fn vec<T, F>(&mut self, mut f: F) -> Result<Vec<T>, Error>
+    pub fn vec<T, E, F>(&mut self, mut f: F) -> Result<Vec<T>, E>
     where
         T: 'a,
-        F: FnMut(&mut Decoder<'a>) -> Result<T, Error> + 'a,
+        E: From<Error>,
+        F: FnMut(&mut Decoder<'a>) -> Result<T, E> + 'a,
     {
         let mut list = self.list()?;
-        let count = {
-            let mut list = Decoder(list.0);
-            let mut count = 0;
-            while list.next()?.is_some() {
-                count += 1;
-            }
-            count
-        };
-        let mut result = Vec::with_capacity(count);
+        let mut result = Vec::with_capacity(list.count()?);
         let mut cursor = list.0;
         while list.next()?.is_some() {
             let size = cursor.len().wrapping_sub(list.0.len());
@@ -65,35 +118,85 @@ impl<'a> Decoder<'a> {
         Ok(result)
     }
 
+    /// Decodes a vector into a caller provided slice without allocating,
+    /// returning the number of decoded items. Errors if the vector has more
+    /// items than fit in `out`.
+    pub fn vec_into<T, F>(&mut self, out: &mut [T], mut f: F) -> Result<usize, Error>
+    where
+        F: FnMut(&mut Decoder<'a>) -> Result<T, Error>,
+    {
+        let capacity = out.len();
+        let mut list = self.list()?;
+        let mut count = 0;
+        let mut cursor = list.0;
+        while list.next()?.is_some() {
+            let size = cursor.len().wrapping_sub(list.0.len());
+            let (item, rest) = unsafe { cursor.split_at_unchecked(size) };
+            let slot = out.get_mut(count).ok_or(Error::TooManyItems { capacity })?;
+            *slot = f(&mut Decoder(item))?;
+            cursor = rest;
+            count += 1;
+        }
+        Ok(count)
+    }
+
     /// Decodes a bytes item.
     pub fn bytes(&mut self) -> Result<&'a [u8], Error> {
-        match self.next()? {
-            Some(Item::Bytes(data)) => Ok(data),
-            _ => Err(Error),
-        }
+        self.item()?.as_bytes().ok_or(Error::Invalid)
     }
 
     /// Decodes a bytes array item.
     pub fn bytes_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
-        self.bytes()?.try_into().map_err(|_| Error)
+        self.bytes()?.try_into().map_err(|_| Error::Invalid)
+    }
+
+    /// Decodes a bytes array item, slicing short byte strings of exactly `N`
+    /// (at least 2) bytes directly and falling back to [`Decoder::bytes_array`] otherwise.
+    pub fn bytes_array_fast<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        // Single bytes are excluded, as they have a non-canonical encoding.
+        if (2..=55).contains(&N)
+            && self.0.first() == Some(&(0x80 + N as u8))
+            && let Some((data, rest)) = self.0.get(1..).and_then(|d| d.split_first_chunk())
+        {
+            self.0 = rest;
+            return Ok(*data);
+        }
+        self.bytes_array()
     }
 
     /// Decodes an address item
     pub fn address(&mut self) -> Result<[u8; 20], Error> {
-        self.bytes_array()
+        self.bytes_array_fast()
+    }
+
+    /// Decodes an address item, additionally accepting an address left-padded
+    /// to a 32-byte word as long as the padding bytes are zero.
+    pub fn address_lenient(&mut self) -> Result<[u8; 20], Error> {
+        let bytes = self.bytes()?;
+        if let Some((padding, address)) = bytes.split_first_chunk::<12>()
+            && let Ok(address) = address.try_into()
+        {
+            return (padding == &[0; 12])
+                .then_some(address)
+                .ok_or(Error::DirtyAddressPadding);
+        }
+        bytes.try_into().map_err(|_| Error::Invalid)
     }
 
     /// Decodes an uint item
     pub fn uint(&mut self) -> Result<[u8; 32], Error> {
-        let mut uint = [0; 32];
+        uint(self.bytes()?)
+    }
+
+    /// Decodes an uint item, additionally returning whether it is zero.
+    ///
+    /// Like [`Decoder::uint`], this errors with [`Error::IntegerTooLarge`] for
+    /// items longer than 32 bytes and accepts leading zero bytes. The zero
+    /// check scans the encoded bytes rather than the decoded 32-byte word.
+    pub fn uint_u256_checked(&mut self) -> Result<([u8; 32], bool), Error> {
         let bytes = self.bytes()?;
-        let offset = 32_usize.checked_sub(bytes.len() as _).ok_or(Error)?;
-        unsafe {
-            bytes
-                .as_ptr()
-                .copy_to_nonoverlapping(uint.as_mut_ptr().add(offset), bytes.len())
-        };
-        Ok(uint)
+        let is_zero = bytes.iter().all(|&b| b == 0);
+        Ok((uint(bytes)?, is_zero))
     }
 
     /// Decodes an boolean item
@@ -101,16 +204,32 @@ impl<'a> Decoder<'a> {
         match self.bytes()? {
             [] => Ok(false),
             [1] => Ok(true),
-            _ => Err(Error),
+            _ => Err(Error::Invalid),
+        }
+    }
+
+    /// Decodes the next item, erroring if there are no items left.
+    fn item(&mut self) -> Result<Item<'a>, Error> {
+        self.next()?.ok_or(Error::UnexpectedEof)
+    }
+
+    /// Returns the number of remaining items, without consuming them.
+    fn count(&self) -> Result<usize, Error> {
+        let mut list = Decoder(self.0);
+        let mut count = 0;
+        while list.next()?.is_some() {
+            count += 1;
         }
+        Ok(count)
     }
 
     /// Ensures a decoder is empty.
     pub fn done(&self) -> Result<(), Error> {
-        self.0.is_empty().then_some(()).ok_or(Error)
+        self.0.is_empty().then_some(()).ok_or(Error::Invalid)
     }
 
     /// Decode the next RLP item.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Result<Option<Item<'a>>, Error> {
         let Some(&tag) = self.0.first() else {
             return Ok(None);
@@ -120,46 +239,866 @@ impl<'a> Decoder<'a> {
             (Item::Bytes(data), rest)
         } else if tag <= 0xbf {
             let (data, rest) = prefixed_len(tag, 0x80, self.0)?;
+            canonical(tag, 0x80, self.0, data)?;
             (Item::Bytes(data), rest)
         } else {
             let (data, rest) = prefixed_len(tag, 0xc0, self.0)?;
+            canonical(tag, 0xc0, self.0, data)?;
             (Item::List(Decoder(data)), rest)
         };
         self.0 = rest;
         Ok(Some(item))
     }
+
+    /// Decode the next RLP item along with the raw bytes, including the
+    /// header, that it was decoded from.
+    pub fn next_with_bytes(&mut self) -> Result<Option<(Item<'a>, &'a [u8])>, Error> {
+        let cursor = self.0;
+        let Some(item) = self.next()? else {
+            return Ok(None);
+        };
+        let size = cursor.len().wrapping_sub(self.0.len());
+        let (raw, _) = unsafe { cursor.split_at_unchecked(size) };
+        Ok(Some((item, raw)))
+    }
+}
+
+/// A reader of consecutive top-level RLP items from a stream, buffering one
+/// item at a time.
+///
+/// Each item is read in full into an internal buffer before it is decoded. Its
+/// header is read first in order to determine its length, so that exactly the
+/// bytes of the item are consumed from the stream. This bounds the buffered
+/// data by the largest item rather than the whole stream, but it does not
+/// decode nested items before their enclosing item has been read.
+pub struct FrameReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R: BufRead> FrameReader<R> {
+    /// Create a new RLP frame reader.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Reads and decodes the next RLP item, or `None` at the end of the
+    /// stream.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> io::Result<Option<Item<'_>>> {
+        if !self.read_item()? {
+            return Ok(None);
+        }
+        Decoder::new(&self.buf).next().map_err(invalid_data)
+    }
+
+    /// Reads the raw bytes of the next RLP item, including its header, or
+    /// `None` at the end of the stream.
+    pub fn next_frame(&mut self) -> io::Result<Option<&[u8]>> {
+        if !self.read_item()? {
+            return Ok(None);
+        }
+        // Validate the item, including its canonical encoding.
+        Decoder::new(&self.buf).next().map_err(invalid_data)?;
+        Ok(Some(&self.buf))
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_item(&mut self) -> io::Result<bool> {
+        self.buf.clear();
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(false);
+        }
+
+        let mut tag = [0];
+        self.reader.read_exact(&mut tag)?;
+        self.buf.push(tag[0]);
+        let Some(offset) = tag_offset(tag[0]) else {
+            return Ok(true);
+        };
+
+        let llen = len_of_len(tag[0], offset).map_err(invalid_data)?;
+        self.read_exact(llen)?;
+        let len = payload_len(tag[0], offset, &self.buf[1..]).map_err(invalid_data)?;
+        self.read_exact(len)?;
+        Ok(true)
+    }
+
+    fn read_exact(&mut self, len: usize) -> io::Result<()> {
+        let start = self.buf.len();
+        let read = (&mut self.reader)
+            .take(len as _)
+            .read_to_end(&mut self.buf)?;
+        if read < len {
+            self.buf.truncate(start);
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(())
+    }
+}
+
+fn invalid_data(err: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// An RLP encoder.
+#[derive(Default)]
+pub struct Encoder(Vec<u8>);
+
+impl Encoder {
+    /// Create a new RLP encoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encodes a struct as an RLP list.
+    pub fn list<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Encoder),
+    {
+        let mut list = Encoder::new();
+        f(&mut list);
+        self.header(0xc0, list.0.len());
+        self.0.extend_from_slice(&list.0);
+    }
+
+    /// Encodes a bytes item.
+    pub fn bytes(&mut self, data: &[u8]) {
+        match data {
+            [b] if *b < 0x80 => self.0.push(*b),
+            _ => {
+                self.header(0x80, data.len());
+                self.0.extend_from_slice(data);
+            }
+        }
+    }
+
+    /// Encodes an uint item, without leading zeros.
+    pub fn uint(&mut self, uint: [u8; 32]) {
+        let zeros = uint.iter().take_while(|&&b| b == 0).count();
+        self.bytes(&uint[zeros..]);
+    }
+
+    /// Returns the encoded bytes.
+    pub fn finish(self) -> Vec<u8> {
+        self.0
+    }
+
+    fn header(&mut self, offset: u8, len: usize) {
+        if len <= 55 {
+            self.0.push(offset + len as u8);
+        } else {
+            let be = len.to_be_bytes();
+            let zeros = be.iter().take_while(|&&b| b == 0).count();
+            self.0.push(offset + 55 + (be.len() - zeros) as u8);
+            self.0.extend_from_slice(&be[zeros..]);
+        }
+    }
+}
+
+/// Returns the encoded length of a bytes item, as encoded by
+/// [`Encoder::bytes`].
+pub fn bytes_len(data: &[u8]) -> usize {
+    match data {
+        [b] if *b < 0x80 => 1,
+        _ => header_len(data.len()) + data.len(),
+    }
+}
+
+/// Returns the encoded length of an uint item, as encoded by
+/// [`Encoder::uint`].
+pub fn uint_len(uint: &[u8; 32]) -> usize {
+    let zeros = uint.iter().take_while(|&&b| b == 0).count();
+    bytes_len(&uint[zeros..])
+}
+
+/// Returns the encoded length of a list item with a payload of
+/// `payload_len` encoded bytes, as encoded by [`Encoder::list`].
+pub fn list_len(payload_len: usize) -> usize {
+    header_len(payload_len) + payload_len
+}
+
+fn header_len(len: usize) -> usize {
+    if len <= 55 {
+        1
+    } else {
+        1 + (usize::BITS - len.leading_zeros()).div_ceil(8) as usize
+    }
+}
+
+fn uint(bytes: &[u8]) -> Result<[u8; 32], Error> {
+    let mut uint = [0; 32];
+    let offset = 32_usize
+        .checked_sub(bytes.len() as _)
+        .ok_or(Error::IntegerTooLarge { got: bytes.len() })?;
+    unsafe {
+        bytes
+            .as_ptr()
+            .copy_to_nonoverlapping(uint.as_mut_ptr().add(offset), bytes.len())
+    };
+    Ok(uint)
+}
+
+/// Ensures that an item uses its shortest encoding, as canonical RLP
+/// requires: single bytes below 0x80 are their own encoding, and the long form
+/// is only used for payloads over 55 bytes with no leading zero length bytes.
+fn canonical(tag: u8, offset: u8, encoded: &[u8], data: &[u8]) -> Result<(), Error> {
+    let canonical = if tag > offset + 55 {
+        data.len() > 55 && encoded.get(1) != Some(&0)
+    } else {
+        tag != 0x81 || data.first() >= Some(&0x80)
+    };
+    canonical.then_some(()).ok_or(Error::Invalid)
 }
 
 fn prefixed_len(tag: u8, offset: u8, data: &[u8]) -> Result<(&[u8], &[u8]), Error> {
-    Some(())
-        .and_then(|()| {
-            let long = offset + 55;
-            if tag <= long {
-                let len = (tag - offset) as usize;
-                data.get(1..)?.split_at_checked(len)
-            } else {
-                let llen = (tag - long) as usize;
-                if llen > 4 {
-                    // Too long!
-                    return None;
-                }
-                let lend = llen.wrapping_add(1);
-                let lbytes = data.get(1..lend)?;
-                let len = {
-                    let mut be = [0; 4];
-                    let offset = 4_usize.wrapping_sub(llen as _);
-                    unsafe {
-                        lbytes
-                            .as_ptr()
-                            .copy_to_nonoverlapping(be.as_mut_ptr().add(offset), llen)
+    let lend = len_of_len(tag, offset)?.wrapping_add(1);
+    // The buffer ending within the length prefix indicates a partial buffer
+    // rather than a structurally invalid encoding.
+    let lbytes = data.get(1..lend).ok_or(Error::UnexpectedEof)?;
+    let len = payload_len(tag, offset, lbytes)?;
+    data.get(lend..)
+        .and_then(|data| data.split_at_checked(len))
+        .ok_or(Error::Invalid)
+}
+
+/// Returns the number of length bytes following a tag, which is zero for
+/// short form items.
+fn len_of_len(tag: u8, offset: u8) -> Result<usize, Error> {
+    let long = offset + 55;
+    if tag <= long {
+        return Ok(0);
+    }
+    let llen = (tag - long) as usize;
+    if llen > 4 {
+        // Too long!
+        return Err(Error::Invalid);
+    }
+    Ok(llen)
+}
+
+/// Returns the payload length of an item from its tag and length bytes.
+fn payload_len(tag: u8, offset: u8, lbytes: &[u8]) -> Result<usize, Error> {
+    let llen = lbytes.len();
+    if llen == 0 {
+        return Ok((tag - offset) as usize);
+    }
+    let mut be = [0; 4];
+    let offset = 4_usize.wrapping_sub(llen as _);
+    unsafe {
+        lbytes
+            .as_ptr()
+            .copy_to_nonoverlapping(be.as_mut_ptr().add(offset), llen)
+    };
+    // The length is checked against the remaining input by the caller.
+    Ok(u32::from_be_bytes(be) as usize)
+}
+
+/// Returns the length offset of a tag for items with a length prefix.
+fn tag_offset(tag: u8) -> Option<u8> {
+    match tag {
+        0x00..=0x7f => None,
+        0x80..=0xbf => Some(0x80),
+        0xc0..=0xff => Some(0xc0),
+    }
+}
+
+/// An RLP decoding error.
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid RLP encoding.
+    Invalid,
+    /// An integer item is longer than 32 bytes.
+    IntegerTooLarge {
+        /// The length of the integer item in bytes.
+        got: usize,
+    },
+    /// A list has more items than the provided capacity.
+    TooManyItems {
+        /// The capacity for decoded items.
+        capacity: usize,
+    },
+    /// The input ends within an item's length prefix, or a list ends before
+    /// an expected item.
+    UnexpectedEof,
+    /// An address padded to a 32-byte word has non-zero padding bytes.
+    DirtyAddressPadding,
+    /// A struct is encoded as a byte string instead of a list.
+    ExpectedList,
+    /// A struct list has more items than were decoded.
+    TrailingData {
+        /// The number of remaining items.
+        remaining: usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Invalid => f.write_str("invalid RLP encoding"),
+            Self::IntegerTooLarge { got } => {
+                write!(f, "RLP integer of {got} bytes exceeds 32 bytes")
+            }
+            Self::TooManyItems { capacity } => {
+                write!(f, "RLP list exceeds capacity of {capacity} items")
+            }
+            Self::UnexpectedEof => f.write_str("RLP input ends unexpectedly"),
+            Self::DirtyAddressPadding => f.write_str("RLP address has non-zero padding bytes"),
+            Self::ExpectedList => f.write_str(
+                "expected an RLP list but found a byte string; is the input encoded twice?",
+            ),
+            Self::TrailingData { remaining } => {
+                write!(f, "RLP struct has {remaining} trailing items")
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::u256;
+
+    #[test]
+    fn test_error() {
+        let err: Box<dyn error::Error> = Decoder::new(b"\xc0").bytes().unwrap_err().into();
+        assert_eq!(err.to_string(), "invalid RLP encoding");
+    }
+
+    #[test]
+    fn test_truncated_header() {
+        for encoded in [&b"\xba"[..], b"\xba\x01", b"\xba\x01\x00", b"\xfa\x01\x00"] {
+            assert!(matches!(
+                Decoder::new(encoded).next(),
+                Err(Error::UnexpectedEof),
+            ));
+        }
+        // A complete length prefix with a truncated payload is invalid.
+        assert!(matches!(
+            Decoder::new(b"\xba\x01\x00\x00\x42").next(),
+            Err(Error::Invalid),
+        ));
+
+        let err: Box<dyn error::Error> = Decoder::new(b"\xf9\x01").bytes().unwrap_err().into();
+        assert_eq!(err.to_string(), "RLP input ends unexpectedly");
+    }
+
+    #[test]
+    fn test_frame_reader() {
+        let combined = crate::tests::combined();
+        let stream = [&combined[..], &combined[..], b"\x42"].concat();
+
+        // Feed the stream through a single byte buffer, so that every frame is
+        // assembled from many reads.
+        let mut reader = FrameReader::new(io::BufReader::with_capacity(1, stream.as_slice()));
+        for _ in 0..2 {
+            let raw = reader.next_frame().unwrap().unwrap();
+            assert_eq!(raw, combined);
+            assert!(crate::circuit(&crate::Input::decode(raw).unwrap()).is_ok());
+        }
+        assert!(matches!(reader.next().unwrap(), Some(Item::Bytes(b"\x42"))));
+        assert!(reader.next().unwrap().is_none());
+
+        let mut reader = FrameReader::new(&combined[..combined.len() - 1]);
+        let err = reader.next().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        let mut reader = FrameReader::new(&b"\x81\x01"[..]);
+        let err = reader.next().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_decode_fixed() {
+        let encoded = b"\xc6\x83kid\x01\xc0";
+        let [kid, key, epk] = Decoder::new(encoded).decode_fixed().unwrap();
+        assert!(matches!(kid, Item::Bytes(b"kid")));
+        assert!(matches!(key, Item::Bytes(&[1])));
+        assert!(matches!(epk, Item::List(list) if list.done().is_ok()));
+
+        assert!(Decoder::new(encoded).decode_fixed::<2>().is_err());
+        assert!(Decoder::new(encoded).decode_fixed::<4>().is_err());
+    }
+
+    #[test]
+    fn test_encoder() {
+        let mut encoder = Encoder::new();
+        encoder.list(|list| {
+            list.bytes(b"");
+            list.bytes(b"\x42");
+            list.bytes(b"\x80");
+            list.uint([0; 32]);
+            list.uint({
+                let mut uint = [0; 32];
+                uint[30] = 0x05;
+                uint[31] = 0x39;
+                uint
+            });
+            list.bytes(&[0x42; 56]);
+            list.list(|_| {});
+        });
+        let encoded = encoder.finish();
+        assert_eq!(
+            &encoded[..11],
+            b"\xf8\x43\x80\x42\x81\x80\x80\x82\x05\x39\xb8"
+        );
+
+        let mut list = Decoder::new(&encoded).list().unwrap();
+        assert_eq!(list.bytes().unwrap(), b"");
+        assert_eq!(list.bytes().unwrap(), b"\x42");
+        assert_eq!(list.bytes().unwrap(), b"\x80");
+        assert_eq!(list.uint().unwrap(), [0; 32]);
+        assert_eq!(list.uint().unwrap()[30..], [0x05, 0x39]);
+        assert_eq!(list.bytes().unwrap(), [0x42; 56]);
+        assert!(list.list().unwrap().done().is_ok());
+        assert!(list.done().is_ok());
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        for len in [0, 1, 55, 56, 255, 256, 0x10000] {
+            let data = vec![0x80; len];
+            let mut encoder = Encoder::new();
+            encoder.bytes(&data);
+            assert_eq!(bytes_len(&data), encoder.finish().len());
+
+            let mut encoder = Encoder::new();
+            encoder.list(|list| list.0.extend_from_slice(&data));
+            assert_eq!(list_len(len), encoder.finish().len());
+        }
+        assert_eq!(bytes_len(&[0x7f]), 1);
+
+        for uint in [
+            [0; 32],
+            u256::from_u64(0x7f),
+            u256::from_u64(0x80),
+            [0xff; 32],
+        ] {
+            let mut encoder = Encoder::new();
+            encoder.uint(uint);
+            assert_eq!(uint_len(&uint), encoder.finish().len());
+        }
+    }
+
+    #[test]
+    fn test_next_with_bytes() {
+        let public = crate::hex::decode(crate::tests::PUBLIC).unwrap();
+        let mut list = Decoder::new(&public).list().unwrap();
+        let mut count = 0;
+        while let Some((item, raw)) = list.next_with_bytes().unwrap() {
+            let mut decoder = Decoder::new(raw);
+            let redecoded = decoder.next().unwrap().unwrap();
+            assert!(decoder.done().is_ok());
+            match (item, redecoded) {
+                (Item::Bytes(a), Item::Bytes(b)) => assert_eq!(a, b),
+                (Item::List(a), Item::List(b)) => assert_eq!(a.0, b.0),
+                _ => panic!("raw bytes decode to a different item"),
+            }
+            count += 1;
+        }
+        assert_eq!(count, 6);
+
+        let (_, raw) = Decoder::new(b"\x42\x82\x01\x02")
+            .next_with_bytes()
+            .unwrap()
+            .unwrap();
+        assert_eq!(raw, b"\x42");
+        assert!(Decoder::new(b"").next_with_bytes().unwrap().is_none());
+        assert!(Decoder::new(b"\x82\x01").next_with_bytes().is_err());
+    }
+
+    #[test]
+    fn test_bytes_array_fast() {
+        let cases: &[&[u8]] = &[
+            b"\x94\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1",
+            b"\x93\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1\xa1",
+            b"\x94\xa1\xa1",
+            b"\xc0",
+            b"\x42",
+            b"",
+        ];
+        for &case in cases {
+            let fast = Decoder::new(case).bytes_array_fast::<20>();
+            let generic = Decoder::new(case).bytes_array::<20>();
+            match (fast, generic) {
+                (Ok(fast), Ok(generic)) => assert_eq!(fast, generic),
+                (Err(_), Err(_)) => {}
+                _ => panic!("fast and generic paths disagree"),
+            }
+        }
+
+        let mut decoder = Decoder::new(b"\x81\xc2\x42");
+        assert_eq!(decoder.bytes_array_fast::<1>().unwrap(), [0xc2]);
+        assert_eq!(decoder.bytes_array_fast::<1>().unwrap(), [0x42]);
+        assert!(decoder.done().is_ok());
+        assert!(Decoder::new(b"\x81\x42").bytes_array_fast::<1>().is_err());
+    }
+
+    #[test]
+    fn test_item_accessors() {
+        let mut decoder = Decoder::new(b"\x82\x01\x02\xc2\x03\x04");
+
+        let item = decoder.next().unwrap().unwrap();
+        assert!(item.is_bytes());
+        assert!(!item.is_list());
+        assert_eq!(item.as_bytes(), Some([1, 2].as_slice()));
+        assert!(item.as_list().is_none());
+
+        let item = decoder.next().unwrap().unwrap();
+        assert!(item.is_list());
+        assert!(!item.is_bytes());
+        assert_eq!(item.as_bytes(), None);
+        let mut list = item.as_list().unwrap();
+        assert_eq!(list.bytes().unwrap(), [3]);
+        assert_eq!(list.bytes().unwrap(), [4]);
+        assert!(list.done().is_ok());
+
+        assert!(decoder.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_decode_struct_item_count() {
+        let decode = |encoded: &[u8]| {
+            Decoder::new(encoded).decode_struct(|list| Ok((list.uint()?, list.uint()?)))
+        };
+        assert!(decode(b"\xc2\x01\x02").is_ok());
+        assert!(matches!(
+            decode(b"\xc3\x01\x02\x03"),
+            Err(Error::TrailingData { remaining: 1 }),
+        ));
+        assert!(matches!(decode(b"\xc1\x01"), Err(Error::UnexpectedEof)));
+
+        let err: Box<dyn error::Error> = decode(b"\xc4\x01\x02\x03\xc0").unwrap_err().into();
+        assert_eq!(err.to_string(), "RLP struct has 2 trailing items");
+    }
+
+    #[test]
+    fn test_address_lenient() {
+        let address = [0x42; 20];
+
+        let mut encoded = vec![0x94];
+        encoded.extend_from_slice(&address);
+        assert_eq!(Decoder::new(&encoded).address_lenient().unwrap(), address);
+
+        let mut encoded = vec![0xa0];
+        encoded.extend_from_slice(&[0; 12]);
+        encoded.extend_from_slice(&address);
+        assert_eq!(Decoder::new(&encoded).address_lenient().unwrap(), address);
+        assert!(matches!(
+            Decoder::new(&encoded).address(),
+            Err(Error::Invalid)
+        ));
+
+        encoded[1] = 0x01;
+        assert!(matches!(
+            Decoder::new(&encoded).address_lenient(),
+            Err(Error::DirtyAddressPadding),
+        ));
+        assert!(matches!(
+            Decoder::new(&encoded[..32]).address_lenient(),
+            Err(Error::Invalid),
+        ));
+    }
+
+    #[test]
+    fn test_uint_u256_checked() {
+        let mut decoder = Decoder::new(b"\x80\x82\x01\x00");
+        assert_eq!(decoder.uint_u256_checked().unwrap(), ([0; 32], true));
+        assert_eq!(
+            decoder.uint_u256_checked().unwrap(),
+            (u256::from_u64(0x100), false),
+        );
+        assert!(decoder.done().is_ok());
+    }
+
+    #[test]
+    fn test_uint_too_large() {
+        let mut encoded = vec![0xa1];
+        encoded.extend_from_slice(&[0xff; 33]);
+        assert!(matches!(
+            Decoder::new(&encoded).uint(),
+            Err(Error::IntegerTooLarge { got: 33 }),
+        ));
+
+        encoded[0] = 0xa0;
+        assert_eq!(Decoder::new(&encoded[..33]).uint().unwrap(), [0xff; 32]);
+    }
+
+    fn long_string(len_bytes: &[u8], len: usize) -> Vec<u8> {
+        let mut data = vec![0xb7 + len_bytes.len() as u8];
+        data.extend_from_slice(len_bytes);
+        data.resize(data.len() + len, 0x42);
+        data.push(0x01);
+        data
+    }
+
+    #[test]
+    fn test_prefixed_len_short() {
+        let (data, rest) = prefixed_len(0x83, 0x80, b"\x83\x01\x02\x03\x04").unwrap();
+        assert_eq!(data, [1, 2, 3]);
+        assert_eq!(rest, [4]);
+
+        let (data, rest) = prefixed_len(0xc0, 0xc0, b"\xc0").unwrap();
+        assert!(data.is_empty() && rest.is_empty());
+
+        let mut encoded = vec![0xb7];
+        encoded.resize(56, 0x42);
+        let (data, rest) = prefixed_len(0xb7, 0x80, &encoded).unwrap();
+        assert_eq!(data.len(), 55);
+        assert!(rest.is_empty());
+        assert!(prefixed_len(0xb7, 0x80, &encoded[..55]).is_err());
+    }
+
+    #[test]
+    fn test_prefixed_len_long() {
+        for len_bytes in [
+            &[0x38][..],
+            &[0x01, 0x00],
+            &[0x00, 0x01, 0x00],
+            &[0x00, 0x00, 0x01, 0x00],
+        ] {
+            let len = len_bytes.iter().fold(0, |len, &b| (len << 8) | b as usize);
+            let encoded = long_string(len_bytes, len);
+            let (data, rest) = prefixed_len(encoded[0], 0x80, &encoded).unwrap();
+            assert_eq!(data.len(), len);
+            assert_eq!(rest, [0x01]);
+
+            let truncated = &encoded[..encoded.len() - 2];
+            assert!(prefixed_len(encoded[0], 0x80, truncated).is_err());
+        }
+
+        let mut encoded = long_string(&[0x38], 0x38);
+        encoded[0] = 0xf8;
+        let (data, _) = prefixed_len(0xf8, 0xc0, &encoded).unwrap();
+        assert_eq!(data.len(), 0x38);
+    }
+
+    #[test]
+    fn test_prefixed_len_over_cap() {
+        let encoded = long_string(&[0x00, 0x00, 0x00, 0x00, 0x38], 0x38);
+        assert_eq!(encoded[0], 0xbc);
+        assert!(prefixed_len(0xbc, 0x80, &encoded).is_err());
+        assert!(prefixed_len(0xfc, 0xc0, &encoded).is_err());
+    }
+
+    #[test]
+    fn test_prefixed_len_max() {
+        // The largest length representable with the 4 byte cap must be parsed
+        // and then rejected for lack of data, and not truncated or wrapped.
+        let encoded = long_string(&[0xff, 0xff, 0xff, 0xff], 0x100);
+        assert_eq!(encoded[0], 0xbb);
+        assert!(prefixed_len(0xbb, 0x80, &encoded).is_err());
+        assert!(prefixed_len(0xbb, 0x80, &encoded[..3]).is_err());
+    }
+
+    #[test]
+    fn test_prefixed_len_exceeds_input() {
+        // Lengths beyond the remaining input must error rather than wrap when
+        // checking bounds, including those beyond `isize::MAX` on 32-bit
+        // targets.
+        for len_bytes in [[0x80, 0x00, 0x00, 0x00], [0xff, 0xff, 0xff, 0xff]] {
+            let encoded = long_string(&len_bytes, 0x100);
+            assert!(prefixed_len(encoded[0], 0x80, &encoded).is_err());
+            assert!(Decoder::new(&encoded).next().is_err());
+        }
+    }
+
+    /// Differential testing against the `alloy-rlp` decoder, which rejects
+    /// non-canonical encodings as required by the RLP specification
+    /// (Ethereum Yellow Paper, appendix B).
+    #[cfg(feature = "dev")]
+    mod differential {
+        use super::*;
+
+        /// Seeds for the randomized runs. The first seeds reproduced the
+        /// acceptance of non-canonical single byte, short long-form and
+        /// leading zero length encodings.
+        const SEEDS: &[u64] = &[0x5afe, 0x7e57, 0xc0ffee, 0xdecade, 1, 2, 3, 4];
+
+        /// Encodings on which the decoder historically diverged from
+        /// canonical RLP.
+        const DIVERGENCES: &[&[u8]] = &[
+            b"\x81\x00",
+            b"\x81\x7f",
+            b"\xb8\x01\x42",
+            b"\xb8\x37\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\
+              \x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\
+              \x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\x42\
+              \x42\x42\x42",
+            b"\xf8\x00",
+            b"\xb9\x00\x01\x42",
+            b"\xc2\x81\x01",
+        ];
+
+        #[derive(Clone, PartialEq, Eq, Debug)]
+        enum Value {
+            Bytes(Vec<u8>),
+            List(Vec<Value>),
+        }
+
+        struct Rng(u64);
+
+        impl Rng {
+            fn next(&mut self) -> u64 {
+                // xorshift64*
+                self.0 ^= self.0 >> 12;
+                self.0 ^= self.0 << 25;
+                self.0 ^= self.0 >> 27;
+                self.0.wrapping_mul(0x2545f4914f6cdd1d)
+            }
+
+            fn below(&mut self, n: u64) -> usize {
+                (self.next() % n) as _
+            }
+
+            fn value(&mut self, depth: usize) -> Value {
+                if depth < 3 && self.below(3) == 0 {
+                    let len = self.below(5);
+                    Value::List((0..len).map(|_| self.value(depth + 1)).collect())
+                } else {
+                    let len = match self.below(4) {
+                        0 => 0,
+                        1 => 1,
+                        2 => self.below(56),
+                        _ => self.below(300),
                     };
-                    u32::from_be_bytes(be)
+                    let max = [0x80, 0x100][self.below(2)];
+                    Value::Bytes((0..len).map(|_| self.below(max) as u8).collect())
+                }
+            }
+        }
+
+        /// Encodes a value, using a non-canonical encoding for each item
+        /// that has one with probability `1 / noncanonical`.
+        fn encode(value: &Value, rng: &mut Rng, noncanonical: u64) -> Vec<u8> {
+            let (offset, payload) = match value {
+                Value::Bytes(bytes) if bytes.len() == 1 && bytes[0] < 0x80 => {
+                    if rng.below(noncanonical) == 0 {
+                        return vec![0x81, bytes[0]];
+                    }
+                    return bytes.clone();
+                }
+                Value::Bytes(bytes) => (0x80, bytes.clone()),
+                Value::List(items) => (
+                    0xc0,
+                    items
+                        .iter()
+                        .flat_map(|item| encode(item, rng, noncanonical))
+                        .collect(),
+                ),
+            };
+            let len = (payload.len() as u32).to_be_bytes();
+            let mut len = match &len[len.iter().take_while(|&&b| b == 0).count()..] {
+                [] => &[0][..],
+                len => len,
+            };
+            let mut encoded = if payload.len() <= 55 && rng.below(noncanonical) != 0 {
+                len = &[];
+                vec![offset + payload.len() as u8]
+            } else {
+                let padding = if payload.len() <= 55 || rng.below(noncanonical) != 0 {
+                    0
+                } else {
+                    4 - len.len()
                 };
-                data.get(lend..)?.split_at_checked(len as _)
+                let mut header = vec![offset + 55 + (len.len() + padding) as u8];
+                header.resize(1 + padding, 0);
+                header
+            };
+            encoded.extend_from_slice(len);
+            encoded.extend_from_slice(&payload);
+            encoded
+        }
+
+        /// The `alloy-rlp` decoder, used as the reference implementation.
+        mod reference {
+            use super::Value;
+            use alloy_rlp::Header;
+
+            pub fn decode(mut data: &[u8]) -> Option<Value> {
+                let value = item(&mut data)?;
+                data.is_empty().then_some(value)
             }
-        })
-        .ok_or(Error)
-}
 
-#[cfg_attr(debug_assertions, derive(Debug))]
-pub struct Error;
+            fn item(data: &mut &[u8]) -> Option<Value> {
+                let header = Header::decode(data).ok()?;
+                let (mut payload, rest) = data.split_at_checked(header.payload_length)?;
+                *data = rest;
+                if !header.list {
+                    return Some(Value::Bytes(payload.to_vec()));
+                }
+                let mut items = Vec::new();
+                while !payload.is_empty() {
+                    items.push(item(&mut payload)?);
+                }
+                Some(Value::List(items))
+            }
+        }
+
+        fn decode(data: &[u8]) -> Option<Value> {
+            fn value(item: Item) -> Option<Value> {
+                match item {
+                    Item::Bytes(bytes) => Some(Value::Bytes(bytes.to_vec())),
+                    Item::List(mut list) => {
+                        let mut items = Vec::new();
+                        while let Some(item) = list.next().ok()? {
+                            items.push(value(item)?);
+                        }
+                        Some(Value::List(items))
+                    }
+                }
+            }
+
+            let mut decoder = Decoder::new(data);
+            let value = value(decoder.next().ok()??)?;
+            decoder.done().ok()?;
+            Some(value)
+        }
+
+        fn check(encoded: &[u8]) {
+            assert_eq!(
+                decode(encoded),
+                reference::decode(encoded),
+                "divergence on {encoded:02x?}",
+            );
+        }
+
+        #[test]
+        fn test_differential() {
+            for &seed in SEEDS {
+                let mut rng = Rng(seed);
+                for _ in 0..1000 {
+                    let value = rng.value(0);
+
+                    let encoded = encode(&value, &mut rng, u64::MAX);
+                    assert_eq!(reference::decode(&encoded), Some(value.clone()));
+                    check(&encoded);
+
+                    check(&encode(&value, &mut rng, 4));
+
+                    let mut mutated = encoded.clone();
+                    let index = rng.below(mutated.len() as _);
+                    mutated[index] ^= 1 << rng.below(8);
+                    check(&mutated);
+                    check(&encoded[..rng.below(encoded.len() as _)]);
+                }
+            }
+        }
+
+        #[test]
+        fn test_divergences() {
+            for &encoded in DIVERGENCES {
+                assert_eq!(reference::decode(encoded), None);
+                check(encoded);
+            }
+        }
+    }
+}
@@ -1,18 +1,32 @@
 //! Safe smart account data structures and methods.
 
-use crate::rlp;
+use crate::{rlp, shims::BoolExt as _};
 use sha3::{Digest as _, Keccak256};
+use std::{borrow::Cow, error, fmt, str::FromStr};
 
+/// A Safe transaction, excluding its nonce.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
 pub struct SafeTransaction<'a> {
-    to: [u8; 20],
-    value: [u8; 32],
-    data: &'a [u8],
-    operation: Operation,
-    safe_tx_gas: [u8; 32],
-    gas_gas: [u8; 32],
-    gas_price: [u8; 32],
-    gas_token: [u8; 20],
-    refund_reciver: [u8; 20],
+    /// The target address.
+    pub to: [u8; 20],
+    /// The value in wei.
+    pub value: [u8; 32],
+    /// The call data.
+    pub data: Cow<'a, [u8]>,
+    /// The operation.
+    pub operation: Operation,
+    /// The gas limit for the Safe transaction execution.
+    pub safe_tx_gas: [u8; 32],
+    /// The gas costs independent of the Safe transaction execution (called
+    /// `baseGas` by the Safe contracts, or `dataGas` before v1.1.0).
+    pub gas_gas: [u8; 32],
+    /// The gas price used for the refund calculation.
+    pub gas_price: [u8; 32],
+    /// The token used for the refund, or the zero address for Ether.
+    pub gas_token: [u8; 20],
+    /// The refund receiver, or the zero address for `tx.origin`.
+    pub refund_reciver: [u8; 20],
 }
 
 impl<'a> SafeTransaction<'a> {
@@ -22,7 +36,7 @@ impl<'a> SafeTransaction<'a> {
             Ok(SafeTransaction {
                 to: decoder.address()?,
                 value: decoder.uint()?,
-                data: decoder.bytes()?,
+                data: decoder.bytes()?.into(),
                 operation: decoder.bool()?.into(),
                 safe_tx_gas: decoder.uint()?,
                 gas_gas: decoder.uint()?,
@@ -33,29 +47,262 @@ impl<'a> SafeTransaction<'a> {
         })
     }
 
+    /// Converts into an owned Safe transaction, cloning any borrowed data.
+    pub fn into_owned(self) -> SafeTransaction<'static> {
+        SafeTransaction {
+            data: Cow::Owned(self.data.into_owned()),
+            ..self
+        }
+    }
+
+    /// RLP-encodes the Safe transaction.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = rlp::Encoder::new();
+        encoder.list(|fields| {
+            fields.bytes(&self.to);
+            fields.uint(self.value);
+            fields.bytes(&self.data);
+            fields.uint(self.operation.as_word());
+            fields.uint(self.safe_tx_gas);
+            fields.uint(self.gas_gas);
+            fields.uint(self.gas_price);
+            fields.bytes(&self.gas_token);
+            fields.bytes(&self.refund_reciver);
+        });
+        encoder.finish()
+    }
+
     /// Returns the Safe transaction ERC-712 struct hash.
     pub fn struct_hash(&self, nonce: [u8; 32]) -> [u8; 32] {
+        StructHasher::new().struct_hash(self, nonce)
+    }
+
+    /// Returns the Safe transaction ERC-712 struct hash for a pending nonce
+    /// that is supplied separately from the transaction, for example in
+    /// `approveHash` flows.
+    ///
+    /// Errors if no nonce was supplied, instead of silently hashing with a
+    /// default zero nonce.
+    pub fn struct_hash_pending(
+        &self,
+        nonce: Option<[u8; 32]>,
+    ) -> Result<[u8; 32], MissingNonceError> {
+        Ok(self.struct_hash(nonce.ok_or(MissingNonceError)?))
+    }
+
+    /// Returns the Safe transaction ERC-712 struct hash as computed by a
+    /// specific Safe contract version.
+    pub fn struct_hash_for_version(&self, version: SafeVersion, nonce: [u8; 32]) -> [u8; 32] {
+        StructHasher::for_version(version).struct_hash(self, nonce)
+    }
+
+    /// Returns the fields that differ from `other`, in field order, with the
+    /// values of both transactions.
+    pub fn diff<'b>(&'b self, other: &'b Self) -> Vec<FieldDiff<'b>> {
+        let mut diffs = Vec::new();
+        if self.to != other.to {
+            diffs.push(FieldDiff::To(self.to, other.to));
+        }
+        if self.value != other.value {
+            diffs.push(FieldDiff::Value(self.value, other.value));
+        }
+        if self.data != other.data {
+            diffs.push(FieldDiff::Data(&self.data, &other.data));
+        }
+        if self.operation != other.operation {
+            diffs.push(FieldDiff::Operation(self.operation, other.operation));
+        }
+        if self.safe_tx_gas != other.safe_tx_gas {
+            diffs.push(FieldDiff::SafeTxGas(self.safe_tx_gas, other.safe_tx_gas));
+        }
+        if self.gas_gas != other.gas_gas {
+            diffs.push(FieldDiff::GasGas(self.gas_gas, other.gas_gas));
+        }
+        if self.gas_price != other.gas_price {
+            diffs.push(FieldDiff::GasPrice(self.gas_price, other.gas_price));
+        }
+        if self.gas_token != other.gas_token {
+            diffs.push(FieldDiff::GasToken(self.gas_token, other.gas_token));
+        }
+        if self.refund_reciver != other.refund_reciver {
+            diffs.push(FieldDiff::RefundReciver(
+                self.refund_reciver,
+                other.refund_reciver,
+            ));
+        }
+        diffs
+    }
+
+    /// Returns the Safe transaction hash, the ERC-712 digest signed by owners
+    /// and returned by the Safe contract's `getTransactionHash`.
+    pub fn eip712_digest(&self, nonce: [u8; 32], domain_separator: [u8; 32]) -> [u8; 32] {
         let mut hasher = Keccak256::new();
-        hasher.update(
-            b"\xbb\x83\x10\xd4\x86\x36\x8d\xb6\xbd\x6f\x84\x94\x02\xfd\xd7\x3a\
-              \xd5\x3d\x31\x6b\x5a\x4b\x26\x44\xad\x6e\xfe\x0f\x94\x12\x86\xd8",
-        );
-        hasher.update(address_to_word(self.to));
-        hasher.update(self.value);
-        hasher.update(Keccak256::digest(self.data));
-        hasher.update(self.operation.as_word());
-        hasher.update(self.safe_tx_gas);
-        hasher.update(self.gas_gas);
-        hasher.update(self.gas_price);
-        hasher.update(address_to_word(self.gas_token));
-        hasher.update(address_to_word(self.refund_reciver));
+        hasher.update(b"\x19\x01");
+        hasher.update(domain_separator);
+        hasher.update(self.struct_hash(nonce));
+        hasher.finalize().into()
+    }
+}
+
+/// A Safe transaction field that differs between two transactions, with the
+/// value of the first and second transaction respectively.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum FieldDiff<'a> {
+    /// The target address differs.
+    To([u8; 20], [u8; 20]),
+    /// The value differs.
+    Value([u8; 32], [u8; 32]),
+    /// The call data differs.
+    Data(&'a [u8], &'a [u8]),
+    /// The operation differs.
+    Operation(Operation, Operation),
+    /// The Safe transaction gas limit differs.
+    SafeTxGas([u8; 32], [u8; 32]),
+    /// The base gas differs.
+    GasGas([u8; 32], [u8; 32]),
+    /// The gas price differs.
+    GasPrice([u8; 32], [u8; 32]),
+    /// The refund token differs.
+    GasToken([u8; 20], [u8; 20]),
+    /// The refund receiver differs.
+    RefundReciver([u8; 20], [u8; 20]),
+}
+
+impl FieldDiff<'_> {
+    /// Returns the name of the differing field, as used by the Safe contracts.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::To(..) => "to",
+            Self::Value(..) => "value",
+            Self::Data(..) => "data",
+            Self::Operation(..) => "operation",
+            Self::SafeTxGas(..) => "safeTxGas",
+            Self::GasGas(..) => "baseGas",
+            Self::GasPrice(..) => "gasPrice",
+            Self::GasToken(..) => "gasToken",
+            Self::RefundReciver(..) => "refundReceiver",
+        }
+    }
+}
+
+/// Returns the ERC-712 domain separator of a Safe (v1.3.0 and later).
+pub fn domain_separator(chain_id: [u8; 32], safe: [u8; 20]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(
+        b"\x47\xe7\x95\x34\xa2\x45\x95\x2e\x8b\x16\x89\x3a\x33\x6b\x85\xa3\
+          \xd9\xea\x9f\xa8\xc5\x73\xf3\xd8\x03\xaf\xb9\x2a\x79\x46\x92\x18",
+    );
+    hasher.update(chain_id);
+    hasher.update(address_to_word(safe));
+    hasher.finalize().into()
+}
+
+/// A Safe contract version, as far as it affects the transaction struct hash.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub enum SafeVersion {
+    /// Safe contracts before v1.1.0, whose `SafeTx` type names the `baseGas`
+    /// field `dataGas`. The fields are positionally identical, but the
+    /// different type string changes the type hash.
+    Legacy,
+    /// Safe contracts v1.1.0 and later.
+    #[default]
+    Current,
+}
+
+impl SafeVersion {
+    /// The `SafeTx` ERC-712 type hash.
+    fn type_hash(self) -> &'static [u8; 32] {
+        match self {
+            Self::Legacy => {
+                b"\x14\xd4\x61\xbc\x74\x12\x36\x7e\x92\x46\x37\xb3\x63\xc7\xbf\x29\
+                  \xb8\xf4\x7e\x2f\x84\x86\x9f\x44\x26\xe5\x63\x3d\x8a\xf4\x7b\x20"
+            }
+            Self::Current => {
+                b"\xbb\x83\x10\xd4\x86\x36\x8d\xb6\xbd\x6f\x84\x94\x02\xfd\xd7\x3a\
+                  \xd5\x3d\x31\x6b\x5a\x4b\x26\x44\xad\x6e\xfe\x0f\x94\x12\x86\xd8"
+            }
+        }
+    }
+}
+
+/// A Safe transaction ERC-712 struct hasher.
+///
+/// The hasher is seeded with the type hash once and cloned for each hashed
+/// transaction, so it can be reused when hashing batches of transactions.
+#[derive(Clone)]
+pub struct StructHasher(Keccak256);
+
+impl StructHasher {
+    /// Creates a new struct hasher.
+    pub fn new() -> Self {
+        Self::for_version(SafeVersion::Current)
+    }
+
+    /// Creates a new struct hasher for a specific Safe contract version.
+    pub fn for_version(version: SafeVersion) -> Self {
+        let mut hasher = Keccak256::new();
+        hasher.update(version.type_hash());
+        Self(hasher)
+    }
+
+    /// Returns the ERC-712 struct hash of a Safe transaction with `nonce`.
+    pub fn struct_hash(&self, transaction: &SafeTransaction, nonce: [u8; 32]) -> [u8; 32] {
+        let mut hasher = self.0.clone();
+        hasher.update(address_to_word(transaction.to));
+        hasher.update(transaction.value);
+        hasher.update(Keccak256::digest(&transaction.data));
+        hasher.update(transaction.operation.as_word());
+        hasher.update(transaction.safe_tx_gas);
+        hasher.update(transaction.gas_gas);
+        hasher.update(transaction.gas_price);
+        hasher.update(address_to_word(transaction.gas_token));
+        hasher.update(address_to_word(transaction.refund_reciver));
         hasher.update(nonce);
         hasher.finalize().into()
     }
+
+    /// Returns the ERC-712 struct hash of an RLP-encoded Safe transaction with
+    /// `nonce`, feeding the decoded fields directly into the hasher without
+    /// materializing a [`SafeTransaction`].
+    pub fn struct_hash_encoded(
+        &self,
+        encoded: &[u8],
+        nonce: [u8; 32],
+    ) -> Result<[u8; 32], rlp::Error> {
+        let hasher = self.0.clone();
+        let mut hasher = rlp::Decoder::new(encoded).decode_struct(move |decoder| {
+            let mut hasher = hasher;
+            hasher.update(address_to_word(decoder.address()?));
+            hasher.update(decoder.uint()?);
+            hasher.update(Keccak256::digest(decoder.bytes()?));
+            hasher.update(Operation::from(decoder.bool()?).as_word());
+            hasher.update(decoder.uint()?);
+            hasher.update(decoder.uint()?);
+            hasher.update(decoder.uint()?);
+            hasher.update(address_to_word(decoder.address()?));
+            hasher.update(address_to_word(decoder.address()?));
+            Ok(hasher)
+        })?;
+        hasher.update(nonce);
+        Ok(hasher.finalize().into())
+    }
 }
 
+impl Default for StructHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A Safe transaction operation.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
 pub enum Operation {
+    /// A regular call.
     Call,
+    /// A delegatecall.
     Delegatecall,
 }
 
@@ -73,6 +320,41 @@ impl Operation {
     }
 }
 
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Call => "call",
+            Operation::Delegatecall => "delegatecall",
+        })
+    }
+}
+
+impl FromStr for Operation {
+    type Err = ParseOperationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "0" || s.eq_ignore_ascii_case("call") {
+            Ok(Operation::Call)
+        } else if s == "1" || s.eq_ignore_ascii_case("delegatecall") {
+            Ok(Operation::Delegatecall)
+        } else {
+            Err(ParseOperationError)
+        }
+    }
+}
+
+/// An error parsing a Safe transaction operation.
+#[derive(Debug)]
+pub struct ParseOperationError;
+
+impl fmt::Display for ParseOperationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid operation, expected \"call\", \"delegatecall\", 0 or 1")
+    }
+}
+
+impl error::Error for ParseOperationError {}
+
 impl From<bool> for Operation {
     fn from(value: bool) -> Self {
         if value {
@@ -83,6 +365,149 @@ impl From<bool> for Operation {
     }
 }
 
+/// An error computing a struct hash without a nonce.
+#[derive(Debug)]
+pub struct MissingNonceError;
+
+impl fmt::Display for MissingNonceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("missing Safe transaction nonce")
+    }
+}
+
+impl error::Error for MissingNonceError {}
+
+/// A call in a packed `MultiSend` batch.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+pub struct MultiSendCall<'a> {
+    /// The operation.
+    pub operation: Operation,
+    /// The target address.
+    pub to: [u8; 20],
+    /// The value in wei.
+    pub value: [u8; 32],
+    /// The call data.
+    pub data: Cow<'a, [u8]>,
+}
+
+/// Decodes the packed transactions of a `MultiSend` call, the `bytes`
+/// parameter of `multiSend(bytes)`.
+///
+/// Each transaction is encoded as a 1 byte operation, 20 byte target address,
+/// 32 byte value, 32 byte data length and the data itself, with no padding
+/// between transactions.
+pub fn decode_multisend(mut data: &[u8]) -> Result<Vec<MultiSendCall<'_>>, MultiSendError> {
+    fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], MultiSendError> {
+        let (head, tail) = data.split_at_checked(len).ok_or(MultiSendError)?;
+        *data = tail;
+        Ok(head)
+    }
+
+    fn word(data: &mut &[u8]) -> Result<[u8; 32], MultiSendError> {
+        Ok(take(data, 32)?.try_into().unwrap())
+    }
+
+    let mut calls = Vec::new();
+    while !data.is_empty() {
+        let operation = match take(&mut data, 1)? {
+            [0] => Operation::Call,
+            [1] => Operation::Delegatecall,
+            _ => return Err(MultiSendError),
+        };
+        let to = take(&mut data, 20)?.try_into().unwrap();
+        let value = word(&mut data)?;
+        let len = word(&mut data)?;
+        let (high, low) = len.split_at(24);
+        high.iter().all(|&b| b == 0).xok_or(MultiSendError)?;
+        let len = u64::from_be_bytes(low.try_into().unwrap());
+        let len = usize::try_from(len).map_err(|_| MultiSendError)?;
+        calls.push(MultiSendCall {
+            operation,
+            to,
+            value,
+            data: take(&mut data, len)?.into(),
+        });
+    }
+    Ok(calls)
+}
+
+/// An error decoding a packed `MultiSend` batch.
+#[derive(Debug)]
+pub struct MultiSendError;
+
+impl fmt::Display for MultiSendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid packed MultiSend transactions")
+    }
+}
+
+impl error::Error for MultiSendError {}
+
+/// Verifies a Safe owner ECDSA signature of `hash` by `expected_signer`.
+///
+/// Owners sign the Safe transaction hash returned by
+/// [`SafeTransaction::eip712_digest`], which commits to the struct hash. The
+/// signature uses the Safe `r || s || v` encoding, where only ECDSA
+/// signatures with `v` of 27 or 28 are supported. This allows producers to
+/// validate signatures locally before proving; the on-chain verification
+/// remains authoritative.
+#[cfg(feature = "ecrecover")]
+pub fn verify_owner_signature(
+    hash: [u8; 32],
+    signature: &[u8; 65],
+    expected_signer: [u8; 20],
+) -> Result<bool, SignatureError> {
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+    let v = signature[64];
+    let recovery_id = match v {
+        27 | 28 => RecoveryId::from_byte(v - 27).unwrap(),
+        _ => return Err(SignatureError::UnsupportedType { v }),
+    };
+    let signature = Signature::from_slice(&signature[..64]).map_err(|_| SignatureError::Invalid)?;
+    // `ecrecover` accepts high-s signatures, but `k256` only verifies their
+    // normalized form, which recovers with the opposite parity.
+    let (signature, recovery_id) = match signature.normalize_s() {
+        Some(normalized) => (
+            normalized,
+            RecoveryId::new(!recovery_id.is_y_odd(), recovery_id.is_x_reduced()),
+        ),
+        None => (signature, recovery_id),
+    };
+    let key = VerifyingKey::recover_from_prehash(&hash, &signature, recovery_id)
+        .map_err(|_| SignatureError::Invalid)?;
+    let point = key.to_encoded_point(false);
+    let signer = Keccak256::digest(&point.as_bytes()[1..]);
+    Ok(signer[12..] == expected_signer)
+}
+
+/// An error verifying a Safe owner signature.
+#[cfg(feature = "ecrecover")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The signature type is not an ECDSA signature.
+    UnsupportedType {
+        /// The signature `v` byte.
+        v: u8,
+    },
+    /// The ECDSA signature is invalid.
+    Invalid,
+}
+
+#[cfg(feature = "ecrecover")]
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnsupportedType { v } => write!(f, "unsupported Safe signature type v={v}"),
+            Self::Invalid => f.write_str("invalid ECDSA signature"),
+        }
+    }
+}
+
+#[cfg(feature = "ecrecover")]
+impl error::Error for SignatureError {}
+
 fn address_to_word(address: [u8; 20]) -> [u8; 32] {
     let mut word = [0u8; 32];
     unsafe {
@@ -92,3 +517,506 @@ fn address_to_word(address: [u8; 20]) -> [u8; 32] {
     };
     word
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::input, u256};
+
+    #[test]
+    fn test_diff() {
+        let input = input();
+        let transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+        assert!(transaction.diff(&transaction).is_empty());
+
+        let other = SafeTransaction {
+            to: [0xb1; 20],
+            value: u256::from_u64(3),
+            ..transaction.clone()
+        };
+        let diffs = transaction.diff(&other);
+        assert!(
+            diffs
+                == [
+                    FieldDiff::To([0xa1; 20], [0xb1; 20]),
+                    FieldDiff::Value(u256::from_u64(2), u256::from_u64(3)),
+                ]
+        );
+        assert_eq!(
+            diffs.iter().map(FieldDiff::name).collect::<Vec<_>>(),
+            ["to", "value"],
+        );
+    }
+
+    #[test]
+    fn test_struct_hasher() {
+        let input = input();
+        let transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+
+        let hasher = StructHasher::new();
+        assert_eq!(
+            hasher.struct_hash(&transaction, input.public.nonce),
+            input.public.struct_hash,
+        );
+        for nonce in [[0; 32], [0xff; 32], input.public.nonce] {
+            assert_eq!(
+                hasher.struct_hash(&transaction, nonce),
+                transaction.struct_hash(nonce),
+            );
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let input = input();
+        let transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+        assert_eq!(transaction.encode(), *input.private.transaction);
+    }
+
+    #[test]
+    fn test_struct_hash() {
+        let input = input();
+        let transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+
+        assert_eq!(
+            transaction.struct_hash(input.public.nonce),
+            input.public.struct_hash,
+        );
+        assert_ne!(
+            transaction.struct_hash(u256::from_u64(1)),
+            transaction.struct_hash(u256::from_u64(2)),
+        );
+        assert_eq!(
+            transaction
+                .struct_hash_pending(Some(input.public.nonce))
+                .unwrap(),
+            input.public.struct_hash,
+        );
+        assert!(transaction.struct_hash_pending(None).is_err());
+    }
+
+    #[test]
+    fn test_struct_hash_encoded() {
+        let input = input();
+        let transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+
+        let hasher = StructHasher::new();
+        for nonce in [[0; 32], input.public.nonce] {
+            assert_eq!(
+                hasher
+                    .struct_hash_encoded(&input.private.transaction, nonce)
+                    .unwrap(),
+                hasher.struct_hash(&transaction, nonce),
+            );
+        }
+        assert_eq!(
+            StructHasher::for_version(SafeVersion::Legacy)
+                .struct_hash_encoded(&input.private.transaction, input.public.nonce)
+                .unwrap(),
+            transaction.struct_hash_for_version(SafeVersion::Legacy, input.public.nonce),
+        );
+
+        let transaction = &input.private.transaction;
+        assert!(
+            hasher
+                .struct_hash_encoded(&transaction[..transaction.len() - 1], [0; 32])
+                .is_err()
+        );
+        assert!(
+            hasher
+                .struct_hash_encoded(&[transaction, &[0x80][..]].concat(), [0; 32])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_legacy_struct_hash() {
+        let input = input();
+        let transaction = SafeTransaction::decode(&input.private.transaction).unwrap();
+
+        assert_eq!(
+            transaction.struct_hash_for_version(SafeVersion::Current, input.public.nonce),
+            input.public.struct_hash,
+        );
+        assert_eq!(
+            transaction.struct_hash_for_version(SafeVersion::Legacy, input.public.nonce),
+            *b"\x0b\xd5\xba\x1b\x4f\xf3\x4f\x8c\xe1\x63\x38\xab\xb9\x54\x23\xf9\
+               \xf8\x2a\x51\x69\xbc\xa9\xad\xcb\x24\x26\x0c\x21\x0d\x63\x0b\x9c",
+        );
+    }
+
+    #[test]
+    fn test_decode_multisend() {
+        let packed = [
+            &[0][..],
+            &[0xa1; 20],
+            &u256::from_u64(2),
+            &u256::from_u64(4),
+            &[1, 2, 3, 4],
+            &[1],
+            &[0xa2; 20],
+            &u256::from_u64(0),
+            &u256::from_u64(0),
+        ]
+        .concat();
+        assert!(
+            decode_multisend(&packed).unwrap()
+                == [
+                    MultiSendCall {
+                        operation: Operation::Call,
+                        to: [0xa1; 20],
+                        value: u256::from_u64(2),
+                        data: Cow::Borrowed(&[1, 2, 3, 4]),
+                    },
+                    MultiSendCall {
+                        operation: Operation::Delegatecall,
+                        to: [0xa2; 20],
+                        value: u256::from_u64(0),
+                        data: Cow::Borrowed(&[]),
+                    },
+                ]
+        );
+        assert!(decode_multisend(&[]).unwrap().is_empty());
+
+        assert!(decode_multisend(&packed[..packed.len() - 1]).is_err());
+        assert!(decode_multisend(&packed[..88]).is_err());
+        let mut invalid = packed.clone();
+        invalid[0] = 2;
+        assert!(decode_multisend(&invalid).is_err());
+        let mut overflow = packed;
+        overflow[53] = 1;
+        assert!(decode_multisend(&overflow).is_err());
+    }
+
+    #[test]
+    fn test_operation_string() {
+        for operation in [Operation::Call, Operation::Delegatecall] {
+            assert!(operation.to_string().parse::<Operation>().unwrap() == operation);
+        }
+        assert!("Call".parse::<Operation>().unwrap() == Operation::Call);
+        assert!("DELEGATECALL".parse::<Operation>().unwrap() == Operation::Delegatecall);
+        assert!("0".parse::<Operation>().unwrap() == Operation::Call);
+        assert!("1".parse::<Operation>().unwrap() == Operation::Delegatecall);
+        for invalid in ["", "2", "create", "call "] {
+            assert!(invalid.parse::<Operation>().is_err());
+        }
+    }
+    #[cfg(feature = "ecrecover")]
+    #[test]
+    fn test_verify_owner_signature() {
+        // Signed with the private key `1`, whose address is well-known.
+        let hash = input().public.struct_hash;
+        let signer =
+            *b"\x7e\x5f\x45\x52\x09\x1a\x69\x12\x5d\x5d\xfc\xb7\xb8\xc2\x65\x90\x29\x39\x5b\xdf";
+        let signature: [u8; 65] = crate::hex::decode(
+            "0x556a282fe92ccca8a22db8e78ff5cd9154f0e1e22d0511e17d256d1c12637362\
+               61dba1a4b14209c3327efd28adfdb501e9cb3f2026654d1e83eff746b4c7799a1b",
+        )
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+        assert_eq!(verify_owner_signature(hash, &signature, signer), Ok(true));
+        assert_eq!(
+            verify_owner_signature(hash, &signature, [0xa1; 20]),
+            Ok(false)
+        );
+
+        let mut other = hash;
+        other[0] ^= 1;
+        assert_eq!(verify_owner_signature(other, &signature, signer), Ok(false));
+
+        let mut flipped = signature;
+        flipped[64] = 28;
+        assert_eq!(verify_owner_signature(hash, &flipped, signer), Ok(false));
+
+        // The malleable high-s form of the signature with the flipped
+        // recovery id recovers the same signer.
+        let high_s = crate::hex::decode(
+            "0x9e245e5b4ebdf63ccd8102d752024afcd0e39dc688e3531d3be267461b6ec7a7",
+        )
+        .unwrap();
+        flipped[32..64].copy_from_slice(&high_s);
+        assert_eq!(verify_owner_signature(hash, &flipped, signer), Ok(true));
+
+        let mut unsupported = signature;
+        unsupported[64] = 1;
+        assert_eq!(
+            verify_owner_signature(hash, &unsupported, signer),
+            Err(SignatureError::UnsupportedType { v: 1 }),
+        );
+
+        let mut invalid = signature;
+        invalid[..32].fill(0);
+        assert_eq!(
+            verify_owner_signature(hash, &invalid, signer),
+            Err(SignatureError::Invalid),
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serialization {
+    //! JSON serialization with the canonical Safe transaction field names.
+
+    use super::{Operation, SafeTransaction};
+    use crate::{hex, u256};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+    use std::{borrow::Cow, fmt};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Json {
+        to: Address,
+        value: Uint,
+        data: Option<Bytes>,
+        operation: u8,
+        safe_tx_gas: Uint,
+        base_gas: Uint,
+        gas_price: Uint,
+        gas_token: Address,
+        refund_receiver: Address,
+    }
+
+    impl Serialize for SafeTransaction<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            Json {
+                to: Address(self.to),
+                value: Uint(self.value),
+                data: Some(Bytes(self.data.to_vec())),
+                operation: match self.operation {
+                    Operation::Call => 0,
+                    Operation::Delegatecall => 1,
+                },
+                safe_tx_gas: Uint(self.safe_tx_gas),
+                base_gas: Uint(self.gas_gas),
+                gas_price: Uint(self.gas_price),
+                gas_token: Address(self.gas_token),
+                refund_receiver: Address(self.refund_reciver),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for SafeTransaction<'static> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let json = Json::deserialize(deserializer)?;
+            Ok(SafeTransaction {
+                to: json.to.0,
+                value: json.value.0,
+                data: json.data.map(|data| data.0).unwrap_or_default().into(),
+                operation: match json.operation {
+                    0 => Operation::Call,
+                    1 => Operation::Delegatecall,
+                    _ => return Err(de::Error::custom("invalid operation")),
+                },
+                safe_tx_gas: json.safe_tx_gas.0,
+                gas_gas: json.base_gas.0,
+                gas_price: json.gas_price.0,
+                gas_token: json.gas_token.0,
+                refund_reciver: json.refund_receiver.0,
+            })
+        }
+    }
+
+    /// A hex-encoded address.
+    struct Address([u8; 20]);
+
+    impl Serialize for Address {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_str(&hex::encode(&self.0))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Address {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let Bytes(bytes) = Bytes::deserialize(deserializer)?;
+            let address = bytes
+                .try_into()
+                .map_err(|_| de::Error::custom("invalid address length"))?;
+            Ok(Self(address))
+        }
+    }
+
+    /// Hex-encoded bytes.
+    struct Bytes(Vec<u8>);
+
+    impl Serialize for Bytes {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_str(&hex::encode(&self.0))
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Bytes {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let s = <Cow<str>>::deserialize(deserializer)?;
+            let bytes = hex::decode(&s).map_err(|_| de::Error::custom("invalid hex"))?;
+            Ok(Self(bytes))
+        }
+    }
+
+    /// A uint256 encoded as a decimal string, in order to preserve its full
+    /// range. Small JSON numbers are also accepted when deserializing.
+    struct Uint([u8; 32]);
+
+    impl Serialize for Uint {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut word = self.0;
+            let mut digits = Vec::new();
+            loop {
+                let mut rem = 0_u16;
+                for b in &mut word {
+                    let cur = (rem << 8) | *b as u16;
+                    *b = (cur / 10) as u8;
+                    rem = cur % 10;
+                }
+                digits.push(b'0' + rem as u8);
+                if u256::is_zero(&word) {
+                    break;
+                }
+            }
+            let digits = digits.into_iter().rev().map(char::from).collect::<String>();
+            serializer.serialize_str(&digits)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Uint {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl de::Visitor<'_> for Visitor {
+                type Value = Uint;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    f.write_str("a decimal uint256 string or number")
+                }
+
+                fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+                where
+                    E: de::Error,
+                {
+                    let mut word = [0; 32];
+                    word[24..].copy_from_slice(&v.to_be_bytes());
+                    Ok(Uint(word))
+                }
+
+                fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+                where
+                    E: de::Error,
+                {
+                    if v.is_empty() {
+                        return Err(E::custom("empty uint256"));
+                    }
+                    let mut word = [0_u8; 32];
+                    for d in v.bytes() {
+                        let mut carry = match d {
+                            b'0'..=b'9' => (d - b'0') as u16,
+                            _ => return Err(E::custom("invalid decimal digit")),
+                        };
+                        for b in word.iter_mut().rev() {
+                            let cur = *b as u16 * 10 + carry;
+                            *b = cur as u8;
+                            carry = cur >> 8;
+                        }
+                        if carry != 0 {
+                            return Err(E::custom("uint256 overflow"));
+                        }
+                    }
+                    Ok(Uint(word))
+                }
+            }
+
+            deserializer.deserialize_any(Visitor)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_round_trip() {
+            let transaction = SafeTransaction {
+                to: [0xa1; 20],
+                value: [0xff; 32],
+                data: Cow::Borrowed(b"\x03\x04\x05\x06"),
+                operation: Operation::Delegatecall,
+                safe_tx_gas: {
+                    let mut word = [0; 32];
+                    word[31] = 7;
+                    word
+                },
+                gas_gas: [0; 32],
+                gas_price: [0; 32],
+                gas_token: [0xa2; 20],
+                refund_reciver: [0xa3; 20],
+            };
+
+            let json = serde_json::to_value(&transaction).unwrap();
+            assert_eq!(
+                json["value"],
+                "115792089237316195423570985008687907853269984665640564039457584007913129639935",
+            );
+            assert_eq!(json["safeTxGas"], "7");
+            assert_eq!(json["baseGas"], "0");
+            assert_eq!(json["data"], "0x03040506");
+            assert_eq!(json["refundReceiver"], hex::encode(&[0xa3; 20]));
+
+            let decoded = serde_json::from_value::<SafeTransaction>(json).unwrap();
+            assert!(decoded == transaction);
+        }
+
+        #[test]
+        fn test_transaction_service_json() {
+            let json = r#"{
+                "safe": "0x5afe3855358E112B5647B952709E6165e1c1eEEe",
+                "to": "0xD9Db270c1B5E3Bd161E8c8503c55cEABeE709552",
+                "value": "1000000000000000000",
+                "data": null,
+                "operation": 0,
+                "gasToken": "0x0000000000000000000000000000000000000000",
+                "safeTxGas": 0,
+                "baseGas": 0,
+                "gasPrice": "0",
+                "refundReceiver": "0x0000000000000000000000000000000000000000",
+                "nonce": 5
+            }"#;
+            let transaction = serde_json::from_str::<SafeTransaction>(json).unwrap();
+            assert_eq!(
+                transaction.to,
+                *b"\xd9\xdb\x27\x0c\x1b\x5e\x3b\xd1\x61\xe8\xc8\x50\x3c\x55\xce\xab\xee\x70\x95\x52",
+            );
+            assert_eq!(
+                transaction.value[24..],
+                1_000_000_000_000_000_000_u64.to_be_bytes()
+            );
+            assert!(transaction.data.is_empty());
+            assert!(transaction.operation == Operation::Call);
+            assert_eq!(transaction.safe_tx_gas, [0; 32]);
+        }
+    }
+}
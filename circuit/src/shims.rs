@@ -4,6 +4,11 @@
 pub trait BoolExt {
     /// Returns `Ok(())` if the bool is `true`, or `Err(err)` otherwise.
     fn xok_or<E>(self, err: E) -> Result<(), E>;
+
+    /// Returns `Ok(())` if the bool is `true`, or `Err(f())` otherwise.
+    fn xok_or_else<E, F>(self, f: F) -> Result<(), E>
+    where
+        F: FnOnce() -> E;
 }
 
 impl BoolExt for bool {
@@ -11,4 +16,12 @@ impl BoolExt for bool {
     fn xok_or<E>(self, err: E) -> Result<(), E> {
         if self { Ok(()) } else { Err(err) }
     }
+
+    #[inline]
+    fn xok_or_else<E, F>(self, f: F) -> Result<(), E>
+    where
+        F: FnOnce() -> E,
+    {
+        if self { Ok(()) } else { Err(f()) }
+    }
 }
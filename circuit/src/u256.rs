@@ -0,0 +1,58 @@
+//! Big-endian 256-bit unsigned integer helpers.
+//!
+//! These operate on the `[u8; 32]` words used throughout the crate for EVM
+//! `uint256` values, and only depend on `core`.
+
+use core::cmp::Ordering;
+
+/// Compares two integers.
+pub fn cmp(a: &[u8; 32], b: &[u8; 32]) -> Ordering {
+    // Big-endian byte arrays of equal length compare lexicographically in the
+    // same order as the integers they represent.
+    a.cmp(b)
+}
+
+/// Returns whether the integer is zero.
+pub fn is_zero(a: &[u8; 32]) -> bool {
+    a.iter().all(|&b| b == 0)
+}
+
+/// Converts a `u64` into an integer.
+pub const fn from_u64(n: u64) -> [u8; 32] {
+    let mut word = [0; 32];
+    let bytes = n.to_be_bytes();
+    let mut i = 0;
+    while i < 8 {
+        word[24 + i] = bytes[i];
+        i += 1;
+    }
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cmp() {
+        assert_eq!(cmp(&from_u64(2), &from_u64(2)), Ordering::Equal);
+        assert_eq!(cmp(&from_u64(1), &from_u64(2)), Ordering::Less);
+        assert_eq!(cmp(&from_u64(0x100), &from_u64(0xff)), Ordering::Greater);
+
+        let mut high = [0; 32];
+        high[0] = 1;
+        assert_eq!(cmp(&high, &from_u64(u64::MAX)), Ordering::Greater);
+        assert_eq!(cmp(&[0xff; 32], &high), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_is_zero() {
+        assert!(is_zero(&[0; 32]));
+        assert!(is_zero(&from_u64(0)));
+        assert!(!is_zero(&from_u64(1)));
+
+        let mut high = [0; 32];
+        high[0] = 1;
+        assert!(!is_zero(&high));
+    }
+}
@@ -0,0 +1,34 @@
+//! Regression tests for decoder inputs, such as crashes found by fuzzing.
+//!
+//! Each file in `tests/decoder_regressions/` holds raw bytes that must be
+//! rejected by the input decoders without panicking.
+
+use safe_txe_circuit::{Input, PrivateInput, PublicInput};
+use std::{fs, path::Path};
+
+#[test]
+fn test_decoder_regressions() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/decoder_regressions");
+    let mut count = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let data = fs::read(&path).unwrap();
+
+        let name = path.display();
+        assert!(Input::decode(&data).is_err(), "{name}: decoded input");
+        assert!(
+            Input::try_from(data.as_slice()).is_err(),
+            "{name}: decoded borrowed input",
+        );
+        assert!(
+            PublicInput::decode(&data).is_err(),
+            "{name}: decoded public input"
+        );
+        assert!(
+            PrivateInput::decode(&data).is_err(),
+            "{name}: decoded private input"
+        );
+        count += 1;
+    }
+    assert!(count > 0, "no regression inputs");
+}
@@ -13,9 +13,41 @@ import { decode, encode, type TXE } from "./txe.ts";
 type Encrypt = {
 	transaction: SafeTransactionParameters;
 	recipients: CryptoKey[];
+	keyDerivation?: KeyDerivation;
 };
 
-async function contentEncryptionKey(): Promise<Uint8Array> {
+/**
+ * Derive the content encryption key from a high-entropy master secret with
+ * HKDF-SHA256 instead of generating a random one.
+ */
+type KeyDerivation = {
+	master: Uint8Array;
+	context: Uint8Array;
+};
+
+async function contentEncryptionKey(
+	derivation?: KeyDerivation,
+): Promise<Uint8Array> {
+	if (derivation !== undefined) {
+		const master = await crypto.subtle.importKey(
+			"raw",
+			derivation.master,
+			"HKDF",
+			false,
+			["deriveBits"],
+		);
+		const bits = await crypto.subtle.deriveBits(
+			{
+				name: "HKDF",
+				hash: "SHA-256",
+				salt: new Uint8Array(),
+				info: derivation.context,
+			},
+			master,
+			128,
+		);
+		return new Uint8Array(bits);
+	}
 	const cek = new Uint8Array(16);
 	crypto.getRandomValues(cek);
 	return cek;
@@ -41,12 +73,13 @@ async function exportPublicKey(recipient: CryptoKey): Promise<Uint8Array> {
 async function encrypt({
 	transaction,
 	recipients,
+	keyDerivation,
 }: Encrypt): Promise<{ blob: Uint8Array; private: PrivateInput }> {
 	if (recipients.length === 0) {
 		throw new Error("must encrypt to at least one recipient");
 	}
 	const encoded = safe.encode(transaction);
-	const cek = await contentEncryptionKey();
+	const cek = await contentEncryptionKey(keyDerivation);
 
 	const epks = await Promise.all(recipients.map(() => ephemeralPrivateKey()));
 	const jwes = await Promise.all(
@@ -1,5 +1,7 @@
 import assert from "node:assert/strict";
 import fs from "node:fs/promises";
+import os from "node:os";
+import path from "node:path";
 import { describe, it } from "node:test";
 import { WASI } from "node:wasi";
 import { ethers } from "ethers";
@@ -9,6 +11,7 @@ import {
 	encrypt,
 	extract,
 	type Input,
+	type InputArguments,
 } from "../src/index.ts";
 
 const circuit = await fs
@@ -22,11 +25,22 @@ const circuit = await fs
 		const module = await WebAssembly.compile(wasm as BufferSource);
 		const decoder = new TextDecoder();
 
-		return async (input: Input) => {
+		return async (
+			input: Input,
+			{
+				stdin = false,
+				env = {},
+			}: { stdin?: boolean; env?: Record<string, string> } = {},
+		) => {
 			const args = argify(input);
+			const file = stdin ? await combined(args) : null;
 			const wasi = new WASI({
 				version: "preview1",
-				args: ["safe_txe_circuit", args.public, args.private],
+				args: file
+					? ["safe_txe_circuit"]
+					: ["safe_txe_circuit", args.public, args.private],
+				stdin: file?.fd ?? 0,
+				env,
 			});
 			const instance = await WebAssembly.instantiate(module, {
 				env: {
@@ -43,15 +57,36 @@ const circuit = await fs
 					// biome-ignore-start lint/complexity/useLiteralKeys: index signature type
 					args_get: wasi.wasiImport["args_get"],
 					args_sizes_get: wasi.wasiImport["args_sizes_get"],
+					environ_get: wasi.wasiImport["environ_get"],
+					environ_sizes_get: wasi.wasiImport["environ_sizes_get"],
+					fd_read: wasi.wasiImport["fd_read"],
 					proc_exit: wasi.wasiImport["proc_exit"],
 					// biome-ignore-end lint/complexity/useLiteralKeys: index signature type
 				},
 			});
-			const code = wasi.start(instance);
-			return code === 0;
+			try {
+				const code = wasi.start(instance);
+				return code === 0;
+			} finally {
+				await file?.close();
+			}
 		};
 	});
 
+async function combined(args: InputArguments) {
+	const blob = ethers.encodeRlp([
+		ethers.decodeRlp(args.public),
+		ethers.decodeRlp(args.private),
+	]);
+	const dir = await fs.mkdtemp(path.join(os.tmpdir(), "safe-txe-"));
+	const file = path.join(dir, "input");
+	await fs.writeFile(file, ethers.getBytes(blob));
+	// The open file descriptor remains readable after the file is removed.
+	const handle = await fs.open(file);
+	await fs.rm(dir, { recursive: true });
+	return handle;
+}
+
 async function txe() {
 	const transaction = {
 		to: `0x${"a1".repeat(20)}`,
@@ -118,6 +153,19 @@ describe("circuit", { skip: !circuit }, () => {
 			assert.equal(await circuit?.(input), true);
 		});
 
+		it("should verify a valid TXE read from stdin", async () => {
+			const input = await txe();
+			assert.equal(await circuit?.(input, { stdin: true }), true);
+		});
+
+		it("should fail for an unsupported content encryption algorithm", async () => {
+			const input = await txe();
+			assert.equal(
+				await circuit?.(input, { env: { TXE_ENC: "A256GCM" } }),
+				false,
+			);
+		});
+
 		it("should fail if TXE was tamperred with", async () => {
 			for (const modify of [
 				// biome-ignore-start lint/style/noNonNullAssertion: test code
@@ -77,5 +77,37 @@ describe("txe", () => {
 				),
 			);
 		});
+
+		it("derives the content encryption key from a master secret", async () => {
+			const zero = `0x${"00".repeat(20)}` as const;
+			const transaction = {
+				to: zero,
+				value: 0n,
+				data: "0x",
+				operation: 0,
+				safeTxGas: 0n,
+				baseGas: 0n,
+				gasPrice: 0n,
+				gasToken: zero,
+				refundReceiver: zero,
+			} as const;
+			const recipient = (await crypto.subtle.generateKey("X25519", false, [
+				"deriveBits",
+			])) as CryptoKeyPair;
+
+			// RFC 5869, appendix A.3 (empty salt and info), truncated to 16 bytes.
+			const { private: input } = await encrypt({
+				transaction,
+				recipients: [recipient.publicKey],
+				keyDerivation: {
+					master: new Uint8Array(22).fill(0x0b),
+					context: new Uint8Array(),
+				},
+			});
+			assert.deepEqual(
+				input.contentEncryptionKey,
+				ethers.getBytes("0x8da4e775a563c18f715f802a063c5a31"),
+			);
+		});
 	});
 });